@@ -0,0 +1,130 @@
+use std::path::{ Path, PathBuf };
+use clap::{ Parser, Subcommand, CommandFactory, error::ErrorKind };
+use crate::{
+    menu::{ Difficulty, Rules },
+    game::app::MIN_SIZE,
+    game::scores::{ ScoreBoard, SCORES_FILE },
+    io::storage
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "snake", version, about = "Snake in the terminal")]
+pub(crate) struct Cli {
+    #[command(subcommand)]
+    pub(crate) command: Option<CliCommand>,
+
+    #[arg(long, help = "Start a game right away instead of showing the menu")]
+    pub(crate) play: bool,
+
+    #[arg(long, value_enum, default_value_t = Difficulty::Easy, help = "Difficulty of the game started with --play")]
+    pub(crate) difficulty: Difficulty,
+
+    #[arg(long, value_enum, default_value_t = Rules::Wrap, help = "What happens at the border of the field")]
+    pub(crate) rules: Rules,
+
+    #[arg(long, value_name = "WxH", value_parser = parse_size, help = "Size of the field inside the border, e.g. 60x20; the whole window when left out")]
+    pub(crate) size: Option<(u16, u16)>,
+
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["size", "rules"], help = "Play a level instead of the open field")]
+    pub(crate) level: Option<String>,
+
+    #[arg(long, help = "Seed for item placement, to replay the same game")]
+    pub(crate) seed: Option<u64>,
+
+    #[arg(long, value_name = "PATH", help = "Score file to read and update, instead of the one in the data directory")]
+    pub(crate) scores: Option<PathBuf>,
+
+    #[arg(long, value_name = "NAME", help = "Theme to draw with; overrides SNAKE_THEME")]
+    pub(crate) theme: Option<String>,
+
+    #[arg(long, value_name = "PRESET|FILE", help = "Key bindings: a preset name or a key bindings file")]
+    pub(crate) keymap: Option<String>
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+pub(crate) enum CliCommand {
+    #[command(about = "Print the score board and exit")]
+    Scores {
+        #[arg(long, help = "Print the score file as JSON")]
+        json: bool
+    },
+    #[command(about = "Clear every score in the score file")]
+    ResetScores
+}
+
+impl Cli {
+    // Whether a game starts without going through the menu.
+    pub(crate) fn starts_game(&self) -> bool {
+        self.play || self.size.is_some() || self.level.is_some()
+    }
+
+    // The `--scores` file, or the one in the data directory. Scores left in
+    // the current directory by older versions are moved there first.
+    pub(crate) fn scores_path(&self) -> PathBuf {
+        if let Some(path) = &self.scores {
+            return path.clone();
+        }
+
+        let path = ScoreBoard::default_path();
+        storage::adopt(Path::new(SCORES_FILE), &path);
+        path
+    }
+}
+
+pub(crate) fn parse_size(text: &str) -> Result<(u16, u16), String> {
+    let (w, h) = text.split_once(['x', 'X']).ok_or(format!("expected WIDTHxHEIGHT, got '{}'", text))?;
+    let w: u16 = w.trim().parse().map_err(|_| format!("invalid width '{}'", w))?;
+    let h: u16 = h.trim().parse().map_err(|_| format!("invalid height '{}'", h))?;
+
+    if w < MIN_SIZE.0 || h < MIN_SIZE.1 {
+        return Err(format!("the field must be at least {}x{}", MIN_SIZE.0, MIN_SIZE.1));
+    }
+
+    Ok((w, h))
+}
+
+// Reports a bad option the way clap reports its own errors, and exits.
+pub(crate) fn fail(message: String) -> ! {
+    Cli::command().error(ErrorKind::InvalidValue, message).exit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_are_parsed_and_checked() {
+        assert_eq!(parse_size("60x20"), Ok((60, 20)));
+        assert_eq!(parse_size("80X25"), Ok((80, 25)));
+
+        assert!(parse_size("60").is_err());
+        assert!(parse_size("60xtall").is_err());
+        assert!(parse_size("10x5").is_err());
+        assert_eq!(parse_size("34x6"), Ok(MIN_SIZE));
+        assert!(parse_size("34x5").is_err());
+    }
+
+    #[test]
+    fn options_start_a_game_without_the_menu() {
+        let cli = Cli::try_parse_from(["snake", "--difficulty", "hard", "--rules", "walls", "--size", "60x20", "--seed", "7"]).unwrap();
+
+        assert!(cli.starts_game());
+        assert_eq!(cli.difficulty, Difficulty::Hard);
+        assert_eq!(cli.rules, Rules::Walls);
+        assert_eq!(cli.size, Some((60, 20)));
+        assert_eq!(cli.seed, Some(7));
+
+        assert!(!Cli::try_parse_from(["snake", "--seed", "7"]).unwrap().starts_game());
+        assert!(Cli::try_parse_from(["snake", "--level", "Box", "--size", "60x20"]).is_err());
+    }
+
+    #[test]
+    fn score_subcommands() {
+        let cli = Cli::try_parse_from(["snake", "scores", "--json"]).unwrap();
+        assert_eq!(cli.command, Some(CliCommand::Scores { json: true }));
+
+        let cli = Cli::try_parse_from(["snake", "--scores", "other.json", "reset-scores"]).unwrap();
+        assert_eq!(cli.command, Some(CliCommand::ResetScores));
+        assert_eq!(cli.scores_path().to_str(), Some("other.json"));
+    }
+}
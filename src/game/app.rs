@@ -1,241 +1,303 @@
 use termion::{ clear, cursor, terminal_size, AsyncReader };
-use core::ops::ControlFlow;
-use std::{ io::{ Read, Write }, time::{ Instant, Duration }, thread::sleep };
+use std::{ collections::BTreeMap, env, io::Write, time::{ Instant, Duration, SystemTime, UNIX_EPOCH }, thread::sleep, rc::Rc };
+use std::sync::{ Arc, atomic::{ AtomicBool, Ordering } };
+use signal_hook::{ consts::SIGWINCH, flag, low_level };
 use crate::{
-    menu::Difficulty,
+    menu::GameOptions,
     io::wait_char,
+    io::layout,
+    io::screen::Screen,
+    io::theme,
+    io::input::{ InputQueue, ENABLE_FOCUS_EVENTS, DISABLE_FOCUS_EVENTS },
+    io::keymap::{ Keymap, key_name },
+    io::help::show_help,
     io::renderable::*,
-    game::apple::{Apple, AppleType},
-    game::snake::Snake
+    game::level::Level,
+    game::scores::Record,
+    game::scheduler::{ self, Scheduler },
+    game::state::{ GameState, Command, Event, Ending }
 };
 
-enum Command {
-    Up,
-    Down,
-    Left,
-    Right,
-    Quit,
-    None
+// Set to anything to show the bytes sent per frame in the info panel, to
+// measure what the differential renderer saves over a slow connection.
+pub(crate) const FRAME_STATS_VAR: &str = "SNAKE_FRAME_STATS";
+
+// How often input is read and the screen refreshed between game ticks.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+// The smallest field the game over, pause and quit dialogs fit in, border
+// left out. Sizes given for a game are always those of the field, which is
+// also what scores are filed under.
+pub(crate) const MIN_SIZE: (u16, u16) = (34, 6);
+
+// The board frame around a field of `size`.
+pub(crate) fn frame_for(size: (u16, u16)) -> Frame {
+    Frame::new((1, 1), (size.0 + 2, size.1 + 2))
+}
+
+// The window needed by a field of `size`: its frame and the info panel
+// below it.
+pub(crate) fn window_for(size: (u16, u16)) -> (u16, u16) {
+    let frame = frame_for(size);
+    (frame.size.0, frame.size.1 + 3)
+}
+
+// The largest field a `window` has room for, the other way round from
+// `window_for`.
+pub(crate) fn field_for(window: (u16, u16)) -> (u16, u16) {
+    (window.0.saturating_sub(2), window.1.saturating_sub(5))
 }
 
 #[derive(Clone)]
 pub(crate) struct App {
-    frame: Frame,
-    red_apple: Apple,
-    yellow_apple: Apple,
-    snake: Snake,
-    speed: u64,
-    score: u64,
-    game_over: bool,
-    quit: bool,
-    difficulty: Difficulty,
-    size: Option<(u16, u16)>
+    pub(crate) state: GameState,
+    resizable: bool,
+    seed: u64,
+    // Bytes sent for the last frame and on average, shown in the info panel
+    // when `FRAME_STATS_VAR` is set.
+    frame_bytes: Option<(usize, u64)>
 }
 
 // -------------- App impl --------------
 
 impl App {
-    fn new(difficulty: Difficulty, size:Option<(u16, u16)>) -> App {
-        let frame = Frame { pos:(1,1), size: (78,23) };
-
-        let result = App {
-            frame: frame,
-            red_apple: Apple { pos:frame.random_point(), points: 1, inc_speed: 1, apple_type: AppleType::Red, frame: frame },
-            yellow_apple: Apple { pos:frame.random_point(), points: 2, inc_speed: 2, apple_type: AppleType::Yellow, frame: frame },
-            snake: Snake { body: vec![(3,1),(2,1),(1,1)], dir: (1,0), frame },
-            speed: 10,
-            score: 0,
-            game_over: false,
-            quit: false,
-            difficulty,
-            size
+    // Levels come with their own size and snake; the starting length only
+    // applies to the open field.
+    pub(crate) fn new(options: &GameOptions, level: Option<&Level>, seed: u64) -> App {
+        let resizable = options.size.is_none() && level.is_none();
+        let level = match (level, options.size) {
+            (Some(level), _) => level.clone(),
+            (None, Some(size)) => Level::open(size).with_length(options.length),
+            (None, None) => Level::open(App::terminal_frame().field()).with_length(options.length)
         };
 
-        result.update_frame_size()
-    }
-
-    fn update_frame_size(self) -> App {
-        if self.size.is_some() {
-            return self;
-        }
+        let mut state = GameState::new(options.difficulty, options.rules, Rc::new(level), seed);
+        state.speed = options.speed;
 
-        let size = terminal_size().unwrap();
-        let size = (size.0, size.1 - 3);
-        let frame = Frame { pos: (1,1), size };
-
-        if frame == self.frame {
-            return self;
-        }
-
-        App {
-            frame,
-            red_apple: Apple { pos:frame.random_point(), frame, ..self.red_apple },
-            yellow_apple: Apple { pos:frame.random_point(), ..self.yellow_apple },
-            snake: Snake { frame, ..self.snake },
-            ..self
-        }
+        App { state, resizable, seed, frame_bytes: None }
     }
 
-    fn render<W:Write>(&self, stdout: &mut W) {
-        write!(stdout, "{}", clear::All).unwrap();
-        self.frame.render(stdout);
-        self.red_apple.render(stdout);
-        self.yellow_apple.render(stdout);
-        self.snake.render(stdout);
-
-        let info_panel_frame = Frame { pos: (self.frame.pos.0, self.frame.pos.1 + self.frame.size.1), size: (self.frame.size.0, 3) };
-        let info_panel = InfoPanel { score: self.score, speed: self.speed, frame: info_panel_frame };
-        info_panel.render(stdout);
-
-        stdout.flush().unwrap();
+    // The board frame for the current window, leaving room for the info
+    // panel. Never smaller than `MIN_SIZE`, even if the window is.
+    fn terminal_frame() -> Frame {
+        let (w, h) = field_for(terminal_size().unwrap());
+        frame_for((w.max(MIN_SIZE.0), h.max(MIN_SIZE.1)))
     }
 
-    fn check_collision(&self) -> App {
-        let mut result = self.clone();
-        let head_pos = self.snake.head_pos();
-        let mut apple_eaten: Option<AppleType> = None;
-
-        for apple in [&self.red_apple,&self.yellow_apple].iter() {
-            if head_pos.0 == apple.pos.0 && head_pos.1 == apple.pos.1 {
-                result.snake = self.snake.grow(apple.points as u16);
-                result.speed += apple.inc_speed;
-                result.score += apple.points;
-                apple_eaten = Some(apple.apple_type.clone());
-            }
-        }
-
-        if Some(AppleType::Red) == apple_eaten {
-            result.red_apple = Apple::new(1, 1, AppleType::Red, self.frame);
-        }
-
-        if Some(AppleType::Yellow) == apple_eaten {
-            result.yellow_apple = Apple::new(2, 2, AppleType::Yellow, self.frame);
-        }
-
-        for (x,y) in &self.snake.body[1..] {
-            if head_pos.0 == *x && head_pos.1 == *y {
-                result.game_over = true;
-            }
+    fn update_frame_size(self) -> App {
+        if !self.resizable {
+            return self;
         }
 
-        result
+        App { state: self.state.resize(App::terminal_frame()), ..self }
     }
 
-    fn input_cmd(stdin: &mut AsyncReader) -> Command {
-            let mut key_bytes = [0];
-            stdin.read(&mut key_bytes).unwrap();
-
-            match key_bytes[0] {
-                27 => {
-                    stdin.read(&mut key_bytes).unwrap();
-                    stdin.read(&mut key_bytes).unwrap();
-                    match key_bytes[0] {
-                        65 => return Command::Down,
-                        66 => return Command::Up,
-                        67 => return Command::Right,
-                        68 => return Command::Left,
-                        _ => return Command::None
-                    }
-                }
+    pub(crate) fn render<W:Write>(&self, stdout: &mut W) {
+        let state = &self.state;
 
-                b'q' => return Command::Quit,
-                b'w' => return Command::Down,
-                b's' => return Command::Up,
-                b'a' => return Command::Left,
-                b'd' => return Command::Right,
-                _ => return Command::None
-            }
-    }
+        write!(stdout, "{}", clear::All).unwrap();
+        state.frame.render(stdout);
 
-    fn react_to_command(&self, cmd: Command) -> App {
-        let mut result = self.clone();
-        let mut newdir = (0,0);
-
-        match cmd {
-            Command::Quit => result.quit = true,
-            Command::Up     => newdir = (0,1),
-            Command::Down   => newdir = (0,-1),
-            Command::Left   => newdir = (-1,0),
-            Command::Right  => newdir = (1,0),
-            Command::None   => {}
-        }
+        let wall = theme::current().wall.paint();
+        let walls: String = state.level.walls.iter()
+            .map(|(x, y)| format!("{}{}", state.frame.goto(*x, *y), wall))
+            .collect();
+        write!(stdout, "{}", walls).unwrap();
 
-        if newdir == (0,0) {
-            return result;
+        for item in &state.items {
+            item.render(stdout);
         }
+        state.snake.render(stdout);
 
-        if self.difficulty == Difficulty::Hard {
-            result.snake.dir = newdir;
-        } else if newdir.0 != -self.snake.dir.0 && newdir.1 != -self.snake.dir.1 {
-            result.snake.dir = newdir;
-        }
+        let info_panel_frame = Frame { pos: (state.frame.pos.0, state.frame.pos.1 + state.frame.size.1), size: (state.frame.size.0, 3) };
+        let effects = state.effects.iter().map(|active| active.label()).collect();
+        let bonus = state.bonus().map(|item| item.worth());
+        let info_panel = InfoPanel { score: state.score, speed: state.speed, effects, bonus, frame_bytes: self.frame_bytes, frame: info_panel_frame };
+        info_panel.render(stdout);
 
-        result
+        stdout.flush().unwrap();
     }
 
     fn show_game_over_message<W: Write>(&self, stdout: &mut W) {
+        let screen = if self.state.won { WIN_SCREEN } else { GAME_OVER_SCREEN };
         let cp = CenteredPanel {
-            content: Vec::from(GAME_OVER_SCREEN),
-            frame: self.frame                    
+            content: Vec::from(screen),
+            frame: self.state.frame
         };
 
         cp.render(stdout);
-        write!(stdout, "{}", cursor::Goto(1, self.frame.size.1+4)).unwrap();
+        write!(stdout, "{}", cursor::Goto(1, self.state.frame.size.1+4)).unwrap();
     }
 
-    fn wait_next_turn(&self, now: Instant, before: Instant) -> ControlFlow<()> {
-        let mut speed = self.speed;
-        
-        if self.snake.dir.1 != 0 {
-            speed = (speed as f32 / 1.6) as u64;
-        }
+    fn show_pause_message<W: Write>(&self, stdout: &mut W, keymap: &Keymap) {
+        let content = pause_screen(keymap);
+        let cp = CenteredPanel {
+            content: content.iter().map(String::as_str).collect(),
+            frame: self.state.frame
+        };
+
+        cp.render(stdout);
+        stdout.flush().unwrap();
+    }
 
-        let interval = 1000 / speed;
-        let dt = (now.duration_since(before).subsec_nanos() / 1_000_000) as u64;
+    // The window size needed for the board and the info panel below it.
+    fn needed_size(&self) -> (u16, u16) {
+        window_for(self.state.frame.field())
+    }
 
+    fn fits_terminal(&self) -> bool {
+        layout::fits(self.needed_size())
+    }
 
-        if dt < interval {
-            sleep(Duration::from_millis(interval - dt));
-            return ControlFlow::Continue(());
-        }
-        ControlFlow::Break(())
+    fn tick_interval(&self) -> Duration {
+        scheduler::tick_interval(self.state.speed, self.state.snake.dir.1 != 0)
     }
 
-    pub(crate) fn run<W:Write>(stdin: &mut AsyncReader, stdout: &mut W, difficulty: Difficulty, size: Option<(u16, u16)>) -> u64 {
-        let mut app = App::new(difficulty, size);
-        let mut before = Instant::now();
+    // Input and redraws are handled every `POLL_INTERVAL`, game ticks whenever
+    // the scheduler says one is due, so neither waits for the other. The game
+    // pauses on `p`, when the terminal loses focus and while the window is too
+    // small to show the whole board. Resizes are picked up through SIGWINCH
+    // and redrawn at once. Frames are drawn into a `Screen` and only the
+    // cells that changed are sent to the terminal. Returns the game played
+    // and how it ended.
+    pub(crate) fn run<W:Write>(self, stdin: &mut AsyncReader, stdout: &mut W, keymap: &mut Keymap) -> (Record, Ending) {
+        let mut app = self;
+        let mut eaten = BTreeMap::new();
+        // Game time, as the sum of the ticks played.
+        let mut played = Duration::ZERO;
+        let mut scheduler = Scheduler::new(Instant::now());
+        let mut input = InputQueue::new();
+        let mut dirty = true;
+        let mut paused = false;
+        // Quitting is asked about once the window is large enough for the
+        // question, so that a key pressed on the too-small screen cannot
+        // end the game on its own.
+        let mut quit_asked = false;
+        let mut fits = app.fits_terminal();
+        let mut screen = Screen::new(terminal_size().unwrap());
+        let report_bytes = env::var_os(FRAME_STATS_VAR).is_some();
+
+        // Set by the terminal on every resize, so the size does not have to
+        // be polled.
+        let resized = Arc::new(AtomicBool::new(false));
+        let winch = flag::register(SIGWINCH, Arc::clone(&resized)).unwrap();
+
+        write!(stdout, "{}", ENABLE_FOCUS_EVENTS).unwrap();
+
         loop {
-            app = app.update_frame_size();
-  
-            let now = Instant::now();
+            if resized.swap(false, Ordering::Relaxed) {
+                app = app.update_frame_size();
+                fits = app.fits_terminal();
+                screen.resize(terminal_size().unwrap());
+                dirty = true;
+            }
+
+            let was_paused = paused;
+
+            for cmd in input.poll(stdin, keymap) {
+                match cmd {
+                    Command::Quit => quit_asked = true,
+                    Command::Pause => paused = !paused,
+                    Command::FocusLost => paused = true,
+                    Command::Rebind if fits => {
+                        rebind_keys(stdin, stdout, keymap);
+                        screen.invalidate();
+                        input.clear();
+                        paused = true;
+                        dirty = true;
+                    },
+                    Command::Help if fits => {
+                        show_help(stdin, stdout, keymap, Some((app.state.difficulty, app.state.rules)), 0);
+                        screen.invalidate();
+                        input.clear();
+                        paused = true;
+                        dirty = true;
+                    },
+                    _ => {}
+                }
+            }
+
 
-            if let ControlFlow::Continue(_) = app.wait_next_turn(now, before) {
-                continue;
+            paused |= !fits;
+
+            if paused != was_paused {
+                if !paused {
+                    // Keys pressed on the pause screen are not turns, and the
+                    // time spent there is not owed as ticks.
+                    input.clear();
+                    scheduler.resume(Instant::now(), app.tick_interval());
+                }
+                dirty = true;
             }
 
-            before = now;
+            while !paused && !quit_asked && !app.state.game_over && scheduler.due(Instant::now(), app.tick_interval()) {
+                let turn = input.next_turn(&app.state);
+                played += app.tick_interval();
+                let (state, events) = app.state.step(turn);
+                app.state = state;
 
-            app = app.react_to_command(App::input_cmd(stdin));
+                for event in events {
+                    if let Event::ItemEaten(name) = event {
+                        *eaten.entry(name.to_string()).or_insert(0) += 1;
+                    }
+                }
+                dirty = true;
+            }
 
-            app.snake = app.snake.mv();
-            app = app.check_collision();
-            app.render(stdout);
+            if dirty {
+                if !fits {
+                    let quit = keymap.keys(Command::Quit).first().map_or("-".to_string(), |key| key_name(*key));
+                    layout::render_too_small(stdout, app.needed_size(), &quit);
+                    screen.invalidate();
+                } else {
+                    app.render(&mut screen);
+                    if paused {
+                        app.show_pause_message(&mut screen, keymap);
+                    }
+                    let bytes = screen.present(stdout);
+                    if report_bytes {
+                        app.frame_bytes = Some((bytes, screen.average_bytes()));
+                    }
+                }
+                dirty = false;
+            }
 
-            if app.quit && confirm_quit(stdin, stdout, app.frame) {
-                break;
-            } else {
-                app.quit = false;
+            if quit_asked && fits {
+                quit_asked = false;
+                if confirm_quit(stdin, stdout, app.state.frame) {
+                    break;
+                }
+                screen.invalidate();
+                input.clear();
+                scheduler.resume(Instant::now(), app.tick_interval());
+                dirty = true;
             }
 
-            if app.game_over {
+            if app.state.game_over {
                 app.show_game_over_message(stdout);
                 stdout.flush().unwrap();
                 wait_char(stdin);
                 break;
             }
+
+            let wait = if paused { POLL_INTERVAL } else { scheduler.time_to_next(Instant::now()) };
+            sleep(POLL_INTERVAL.min(wait));
         }
 
-        return app.score;
+        low_level::unregister(winch);
+        write!(stdout, "{}", DISABLE_FOCUS_EVENTS).unwrap();
+
+        let record = Record {
+            name: String::new(),
+            score: app.state.score,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs()),
+            length: app.state.snake.len(),
+            duration: played.as_secs(),
+            eaten,
+            seed: app.seed
+        };
+
+        (record, app.state.ending.unwrap_or(Ending::Quit))
     }
 
-}
\ No newline at end of file
+}
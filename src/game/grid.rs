@@ -0,0 +1,41 @@
+// How many snake segments sit on each field cell. Normally that is zero or
+// one, but a ghost snake may overlap itself. Coordinates are field
+// coordinates, starting at 1 like everywhere else; cells outside the field
+// read as empty and ignore writes.
+#[derive(Clone)]
+pub(crate) struct Grid {
+    size: (u16, u16),
+    cells: Vec<u16>
+}
+
+impl Grid {
+    pub(crate) fn new(size: (u16, u16)) -> Grid {
+        Grid { size, cells: vec![0; size.0 as usize * size.1 as usize] }
+    }
+
+    fn index(&self, (x, y): (u16, u16)) -> Option<usize> {
+        let (w, h) = self.size;
+
+        if x < 1 || y < 1 || x > w || y > h {
+            return None;
+        }
+
+        Some((y - 1) as usize * w as usize + (x - 1) as usize)
+    }
+
+    pub(crate) fn get(&self, pos: (u16, u16)) -> bool {
+        self.index(pos).is_some_and(|i| self.cells[i] > 0)
+    }
+
+    pub(crate) fn add(&mut self, pos: (u16, u16)) {
+        if let Some(i) = self.index(pos) {
+            self.cells[i] += 1;
+        }
+    }
+
+    pub(crate) fn remove(&mut self, pos: (u16, u16)) {
+        if let Some(i) = self.index(pos) {
+            self.cells[i] = self.cells[i].saturating_sub(1);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::io::{ renderable::{ Renderable, Frame }, screen::Color, theme };
+use std::io::Write;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum ItemColor {
+    Red,
+    Yellow,
+    Green,
+    Blue,
+    Cyan,
+    Magenta,
+    White
+}
+
+impl ItemColor {
+    pub(crate) fn color(&self) -> Color {
+        match self {
+            ItemColor::Red => Color::Ansi(1),
+            ItemColor::Yellow => Color::Ansi(3),
+            ItemColor::Green => Color::Ansi(2),
+            ItemColor::Blue => Color::Ansi(4),
+            ItemColor::Cyan => Color::Ansi(6),
+            ItemColor::Magenta => Color::Ansi(5),
+            ItemColor::White => Color::Ansi(15)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Effect {
+    // Adds segments at the tail over the next ticks.
+    Grow(u16),
+    // Drops segments from the tail, never below `MIN_LENGTH`.
+    Shrink(u16),
+    SlowDown(u64),
+    // For the given number of ticks the snake can pass through itself.
+    Ghost(u32),
+    // For the given number of ticks points are multiplied by the factor.
+    Multiplier(u64, u32)
+}
+
+impl Effect {
+    // What eating an item with this effect does, for the help screen.
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Effect::Grow(len) => format!("grows by {}", len),
+            Effect::Shrink(len) => format!("shrinks by {}, never below {}", len, MIN_LENGTH),
+            Effect::SlowDown(amount) => format!("slows down by {}", amount),
+            Effect::Ghost(ticks) => format!("passes through itself for {} ticks", ticks),
+            Effect::Multiplier(factor, ticks) => format!("x{} points for {} ticks", factor, ticks)
+        }
+    }
+}
+
+pub(crate) const MIN_LENGTH: usize = 2;
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct ItemKind {
+    pub(crate) name: &'static str,
+    pub(crate) glyph: &'static str,
+    pub(crate) color: ItemColor,
+    // Relative chance of being picked when a new item is placed.
+    pub(crate) weight: u32,
+    // Bonus items show up now and then on top of the regular ones, and
+    // vanish after this many ticks. Their points shrink as time runs out.
+    pub(crate) lifetime: Option<u32>,
+    pub(crate) points: u64,
+    pub(crate) speed_up: u64,
+    pub(crate) effect: Effect
+}
+
+// Everything that can show up on the board. Adding an item is a matter of
+// adding an entry here.
+pub(crate) const ITEM_KINDS: [ItemKind; 8] = [
+    ItemKind { name: "Red apple",    glyph: "❤︎", color: ItemColor::Red,     weight: 12, lifetime: None, points: 1, speed_up: 1, effect: Effect::Grow(1) },
+    ItemKind { name: "Yellow apple", glyph: "❦", color: ItemColor::Yellow,  weight: 6,  lifetime: None, points: 2, speed_up: 2, effect: Effect::Grow(2) },
+    ItemKind { name: "Scissors",     glyph: "✂", color: ItemColor::Cyan,    weight: 2,  lifetime: None, points: 1, speed_up: 0, effect: Effect::Shrink(3) },
+    ItemKind { name: "Snowflake",    glyph: "❄", color: ItemColor::Blue,    weight: 2,  lifetime: None, points: 1, speed_up: 0, effect: Effect::SlowDown(3) },
+    ItemKind { name: "Ghost",        glyph: "☁", color: ItemColor::Green,   weight: 1,  lifetime: None, points: 1, speed_up: 0, effect: Effect::Ghost(60) },
+    ItemKind { name: "Star",         glyph: "✪", color: ItemColor::Magenta, weight: 1,  lifetime: None, points: 1, speed_up: 0, effect: Effect::Multiplier(2, 80) },
+    ItemKind { name: "Diamond",      glyph: "✦", color: ItemColor::White,   weight: 3,  lifetime: Some(60), points: 10, speed_up: 0, effect: Effect::Grow(1) },
+    ItemKind { name: "Gem",          glyph: "◆", color: ItemColor::Cyan,    weight: 1,  lifetime: Some(40), points: 25, speed_up: 0, effect: Effect::Grow(3) }
+];
+
+// Bonus items blink during their last ticks.
+pub(crate) const BLINK_TICKS: u32 = 15;
+
+#[derive(Clone)]
+pub(crate) struct Item {
+    pub(crate) pos: (u16, u16),
+    pub(crate) kind: &'static ItemKind,
+    // Ticks left before a bonus item vanishes.
+    pub(crate) ttl: Option<u32>,
+    pub(crate) frame: Frame
+}
+
+impl Item {
+    pub(crate) fn new(kind: &'static ItemKind, pos: (u16, u16), frame: Frame) -> Item {
+        Item { pos, kind, ttl: kind.lifetime, frame }
+    }
+
+    // Points earned by eating the item now: bonus items lose value
+    // linearly over their lifetime, but are always worth at least one.
+    pub(crate) fn worth(&self) -> u64 {
+        match (self.ttl, self.kind.lifetime) {
+            (Some(ttl), Some(lifetime)) => (self.kind.points * ttl as u64).div_ceil(lifetime as u64).max(1),
+            _ => self.kind.points
+        }
+    }
+}
+
+impl Renderable for Item {
+    fn render<W:Write>(&self, stdout: &mut W) {
+        if self.ttl.is_some_and(|ttl| ttl <= BLINK_TICKS && ttl % 2 == 0) {
+            return;
+        }
+
+        write!(stdout, "{}{}",
+            self.frame.goto(self.pos.0, self.pos.1),
+            theme::current().item(self.kind).paint()).unwrap();
+    }
+}
+
+// A timed effect still running, with the ticks it has left.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ActiveEffect {
+    pub(crate) effect: Effect,
+    pub(crate) remaining: u32
+}
+
+impl ActiveEffect {
+    pub(crate) fn label(&self) -> String {
+        match self.effect {
+            Effect::Ghost(_) => format!("Ghost {}", self.remaining),
+            Effect::Multiplier(factor, _) => format!("x{} {}", factor, self.remaining),
+            _ => String::new()
+        }
+    }
+}
@@ -0,0 +1,166 @@
+// Level files are plain text. A header of `key: value` lines is followed by
+// a `---` separator and then the map, one text row per field row:
+//
+//     name: Pillars
+//     rules: walls
+//     ---
+//     ..........
+//     ..##..##..
+//     ..>.......
+//     ..++++++..
+//
+// Map cells:
+//     `.` or ` `           empty floor
+//     `#`                  wall
+//     `+`                  floor where apples may spawn
+//     `>` `<` `^` `v`      the snake's head and the direction it starts in
+//
+// When no `+` cell is present apples may spawn on any floor cell. The snake
+// starts three segments long, its body trailing behind the head. Levels
+// without a `rules` line are played with walls.
+
+use std::collections::HashSet;
+use crate::{ menu::Rules, io::storage };
+
+const BUILTIN_LEVELS: [&str; 3] = [
+    include_str!("levels/pillars.txt"),
+    include_str!("levels/cross.txt"),
+    include_str!("levels/garden.txt")
+];
+
+pub(crate) const START_LENGTH: i16 = 3;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Level {
+    pub(crate) name: String,
+    pub(crate) size: (u16, u16),
+    pub(crate) rules: Option<Rules>,
+    pub(crate) walls: HashSet<(u16, u16)>,
+    pub(crate) apple_zones: Vec<(u16, u16)>,
+    pub(crate) start: Vec<(u16, u16)>,
+    pub(crate) dir: (i16, i16)
+}
+
+impl Level {
+    // An empty field of the given size, the way the game has always been played.
+    pub(crate) fn open(size: (u16, u16)) -> Level {
+        Level {
+            name: "Open".to_string(),
+            size,
+            rules: None,
+            walls: HashSet::new(),
+            apple_zones: Vec::new(),
+            start: vec![(3,1),(2,1),(1,1)],
+            dir: (1,0)
+        }
+    }
+
+    // The same open field with a snake of `length` segments along its top
+    // row, no longer than the row.
+    pub(crate) fn with_length(self, length: u16) -> Level {
+        let length = length.clamp(1, self.size.0);
+        Level { start: (1..=length).rev().map(|x| (x, 1)).collect(), ..self }
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<Level, String> {
+        let mut lines = text.lines();
+        let mut name = None;
+        let mut rules = None;
+
+        for line in lines.by_ref() {
+            let line = line.trim();
+            if line == "---" {
+                break;
+            }
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = line.split_once(':').ok_or(format!("invalid header line '{}'", line))?;
+            match (key.trim(), value.trim()) {
+                ("name", value) => name = Some(value.to_string()),
+                ("rules", "wrap") => rules = Some(Rules::Wrap),
+                ("rules", "walls") => rules = Some(Rules::Walls),
+                (key, value) => return Err(format!("unknown header '{}: {}'", key, value))
+            }
+        }
+
+        let mut rows: Vec<Vec<char>> = lines.map(|line| line.chars().collect()).collect();
+        while rows.last().is_some_and(|row| row.is_empty()) {
+            rows.pop();
+        }
+
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let height = rows.len();
+
+        if width == 0 || height == 0 {
+            return Err("the map is empty".to_string());
+        }
+
+        let mut walls = HashSet::new();
+        let mut apple_zones = Vec::new();
+        let mut head = None;
+
+        for (y, row) in (1..).zip(&rows) {
+            for (x, cell) in (1..).zip(row) {
+                match cell {
+                    '.' | ' ' => {},
+                    '#' => { walls.insert((x, y)); },
+                    '+' => apple_zones.push((x, y)),
+                    '>' | '<' | '^' | 'v' if head.is_some() => return Err("more than one start position".to_string()),
+                    '>' => head = Some(((x, y), (1, 0))),
+                    '<' => head = Some(((x, y), (-1, 0))),
+                    '^' => head = Some(((x, y), (0, -1))),
+                    'v' => head = Some(((x, y), (0, 1))),
+                    other => return Err(format!("unknown map cell '{}' at {},{}", other, x, y))
+                }
+            }
+        }
+
+        let ((x, y), dir): ((u16, u16), (i16, i16)) = head.ok_or("missing start position")?;
+        let size = (width as u16, height as u16);
+
+        let start: Vec<(u16, u16)> = (0..START_LENGTH)
+            .map(|i| (x as i16 - dir.0 * i, y as i16 - dir.1 * i))
+            .map(|(x, y)| {
+                let in_field = x >= 1 && y >= 1 && x <= size.0 as i16 && y <= size.1 as i16;
+                if in_field && !walls.contains(&(x as u16, y as u16)) {
+                    Ok((x as u16, y as u16))
+                } else {
+                    Err("no room for the snake's body behind the start position".to_string())
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Level {
+            name: name.ok_or("missing 'name' header")?,
+            size,
+            rules,
+            walls,
+            apple_zones,
+            start,
+            dir
+        })
+    }
+
+    pub(crate) fn builtin() -> Vec<Level> {
+        BUILTIN_LEVELS.iter().map(|text| Level::parse(text).unwrap()).collect()
+    }
+
+    // Built-in levels followed by those found in the `levels` directory
+    // under `storage::data_dir()`.
+    pub(crate) fn all() -> Vec<Level> {
+        let mut levels = Level::builtin();
+        levels.extend(storage::load_dir(&storage::data_dir().join("levels"), Level::parse));
+        levels
+    }
+
+    // The rules the level is played with.
+    pub(crate) fn rules(&self) -> Rules {
+        self.rules.unwrap_or(Rules::Walls)
+    }
+
+    pub(crate) fn is_wall(&self, pos: (u16, u16)) -> bool {
+        self.walls.contains(&pos)
+    }
+}
@@ -1,4 +1,14 @@
 pub(crate) mod app;
 pub(crate) mod scores;
+pub(crate) mod stats;
 pub(crate) mod snake;
-pub(crate) mod apple;
\ No newline at end of file
+pub(crate) mod items;
+pub(crate) mod state;
+pub(crate) mod level;
+pub(crate) mod grid;
+pub(crate) mod scheduler;
+
+#[cfg(test)]
+pub(crate) mod sim;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,62 @@
+use std::time::{ Duration, Instant };
+
+// Ticks that may be played back to back to catch up after a hiccup. When
+// the game falls further behind than this it skips ahead instead.
+const MAX_CATCH_UP: u32 = 5;
+
+// Moving vertically is slower, since terminal cells are taller than wide.
+const VERTICAL_SLOWDOWN: f64 = 1.6;
+
+// Time between two ticks at the given speed, in ticks per second.
+pub(crate) fn tick_interval(speed: u64, vertical: bool) -> Duration {
+    let mut speed = speed.max(1) as f64;
+
+    if vertical {
+        speed /= VERTICAL_SLOWDOWN;
+    }
+
+    Duration::from_secs_f64(1.0 / speed)
+}
+
+// Fixed-timestep clock: each tick is due one interval after the previous
+// one was due, not after it was played, so delays do not add up.
+pub(crate) struct Scheduler {
+    next_tick: Instant,
+    behind: u32
+}
+
+impl Scheduler {
+    pub(crate) fn new(now: Instant) -> Scheduler {
+        Scheduler { next_tick: now, behind: 0 }
+    }
+
+    // Whether a tick is due at `now`. Call it until it returns false, playing
+    // one tick each time it returns true.
+    pub(crate) fn due(&mut self, now: Instant, interval: Duration) -> bool {
+        if now < self.next_tick {
+            self.behind = 0;
+            return false;
+        }
+
+        self.behind += 1;
+        if self.behind > MAX_CATCH_UP {
+            self.next_tick = now + interval;
+            self.behind = 0;
+            return false;
+        }
+
+        self.next_tick += interval;
+        true
+    }
+
+    pub(crate) fn time_to_next(&self, now: Instant) -> Duration {
+        self.next_tick.saturating_duration_since(now)
+    }
+
+    // Starts counting again from `now`, e.g. after the game sat behind a
+    // dialog: the time spent there is not owed as ticks.
+    pub(crate) fn resume(&mut self, now: Instant, interval: Duration) {
+        self.next_tick = now + interval;
+        self.behind = 0;
+    }
+}
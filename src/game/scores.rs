@@ -1,105 +1,434 @@
-use termion::{color, terminal_size, cursor};
-use std::io::{Read, Write};
-use crate::{menu::{MenuAction, Difficulty}, io::renderable::Renderable};
+use termion::{terminal_size, cursor};
+use std::{ collections::BTreeMap, fmt, fs, io::{ self, Write }, path::{ Path, PathBuf } };
+use crate::{menu::{Difficulty, Rules}, io::renderable::Renderable, io::theme, io::storage::{ self, ParseError }};
 use serde::{Deserialize, Serialize};
+use serde_json::{ json, Value };
 
 
 
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+// How many games each leaderboard keeps.
+pub(crate) const LEADERBOARD_SIZE: usize = 10;
+
+pub(crate) const MAX_NAME_LEN: usize = 12;
+
+// A finished game, as kept on a leaderboard.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Record {
+    pub(crate) name: String,
+    pub(crate) score: u64,
+    // Seconds since the Unix epoch at the end of the game; 0 for records
+    // carried over from files that did not keep the details below.
+    pub(crate) timestamp: u64,
+    pub(crate) length: usize,
+    // Seconds played, pauses left out.
+    pub(crate) duration: u64,
+    // How many of each item kind were eaten, by name.
+    pub(crate) eaten: BTreeMap<String, u32>,
+    pub(crate) seed: u64
+}
+
+impl Record {
+    // Who, how much, how long and when, in one line.
+    pub(crate) fn summary(&self) -> String {
+        let name = if self.name.is_empty() { "-" } else { &self.name };
+        let line = format!("{:MAX_NAME_LEN$} {:>5}", name, self.score);
+
+        if self.timestamp == 0 {
+            return line;
+        }
+
+        format!("{}  len {:>3}  {:>2}:{:02}  {}", line, self.length, self.duration / 60, self.duration % 60, date(self.timestamp))
+    }
+
+    // The seed and what was eaten, to go with `summary`.
+    pub(crate) fn details(&self) -> String {
+        if self.timestamp == 0 {
+            return String::new();
+        }
+
+        let eaten: Vec<String> = self.eaten.iter()
+            .map(|(item, count)| format!("{} {}", count, item.split_whitespace().next().unwrap_or(item).to_lowercase()))
+            .collect();
+
+        match eaten.is_empty() {
+            true => format!("seed {}", self.seed),
+            false => format!("seed {}, ate {}", self.seed, eaten.join(", "))
+        }
+    }
+}
+
+// `timestamp` as a UTC date, `YYYY-MM-DD`, by counting days from 1 March
+// of year 0 in 400 year eras.
+fn date(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// The last score of a mode or level and its best games, best first.
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub(crate) struct LBScore {
     pub(crate) last: u64,
-    pub(crate) best: u64
+    pub(crate) top: Vec<Record>
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+impl LBScore {
+    pub(crate) fn best(&self) -> u64 {
+        self.top.first().map_or(0, |record| record.score)
+    }
+
+    // Whether a game scoring `score` would make it into `top`.
+    fn qualifies(&self, score: u64) -> bool {
+        score > 0 && (self.top.len() < LEADERBOARD_SIZE || self.top.last().is_some_and(|record| score > record.score))
+    }
+
+    // Ties go below the games that got there first.
+    fn add(&mut self, record: Record) {
+        self.last = record.score;
 
-pub(crate) struct Score {
-    pub(crate) score: LBScore,
+        if self.qualifies(record.score) {
+            let at = self.top.partition_point(|other| other.score >= record.score);
+            self.top.insert(at, record);
+            self.top.truncate(LEADERBOARD_SIZE);
+        }
+    }
+}
+
+// The settings scores are kept apart for: the rules, the difficulty and
+// the size of the field played on. Scores from before sizes were recorded
+// have none.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub(crate) struct Mode {
+    pub(crate) rules: Rules,
     pub(crate) difficulty: Difficulty,
     pub(crate) size: Option<(u16, u16)>
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+impl Mode {
+    // The rules and difficulty, which the score board groups modes by.
+    fn group(&self) -> String {
+        format!("{} {}", self.rules, self.difficulty)
+    }
+
+    fn size_label(&self) -> String {
+        match self.size {
+            Some((w, h)) => format!("{}x{}", w, h),
+            None => "full window".to_string()
+        }
+    }
+
+    fn label(&self) -> String {
+        format!("{} {}", self.group(), self.size_label())
+    }
+}
+
+// What a game counts towards: a mode, or a level.
+#[derive(Clone, Copy)]
+pub(crate) enum ScoreKey<'a> {
+    Mode(Mode),
+    Level(&'a str)
+}
+
+impl ScoreKey<'_> {
+    // What lifetime statistics are kept under: the rules and difficulty of
+    // a mode, or the level.
+    pub(crate) fn group(&self) -> String {
+        match self {
+            ScoreKey::Mode(mode) => mode.group(),
+            ScoreKey::Level(name) => format!("Level {}", name)
+        }
+    }
+}
+
+// A mode and its scores, as stored.
+#[derive(Clone, Serialize, Deserialize)]
+struct Score {
+    score: LBScore,
+    #[serde(flatten)]
+    mode: Mode
+}
+
+// One row per group, for every pair of rules and difficulty.
+pub(crate) const SCORE_BOARD_ROWS: u16 = 4;
+
+pub(crate) const SCORES_FILE: &str = "scores.json";
+
+// The version of the score file format written by this build. Files
+// without a `version` field are version 1.
+const SCHEMA_VERSION: u64 = 4;
+
+// `MIGRATIONS[n]` turns a version `n + 1` file into a version `n + 2` one.
+const MIGRATIONS: [fn(&mut Value); 3] = [
+    add_rules_and_levels,
+    add_leaderboards,
+    add_field_sizes
+];
+
+// Version 1 files come from before the walls rules and the levels, and may
+// lack both.
+fn add_rules_and_levels(board: &mut Value) {
+    if let Some(scores) = board.get_mut("scores").and_then(Value::as_array_mut) {
+        for score in scores.iter_mut().filter_map(Value::as_object_mut) {
+            score.entry("rules").or_insert(json!(Rules::Wrap));
+        }
+    }
+
+    if let Some(board) = board.as_object_mut() {
+        board.entry("levels").or_insert(json!({}));
+    }
+}
+
+// Version 2 files kept only the last and the best score of each mode and
+// level. The best one becomes a leaderboard of a single, nameless game.
+fn add_leaderboards(board: &mut Value) {
+    let upgrade = |score: &mut Value| {
+        let best = score["best"].as_u64().unwrap_or(0);
+        let top = if best > 0 { vec![Record { score: best, ..Record::default() }] } else { Vec::new() };
+        *score = json!({ "last": score["last"].as_u64().unwrap_or(0), "top": top });
+    };
+
+    if let Some(scores) = board.get_mut("scores").and_then(Value::as_array_mut) {
+        scores.iter_mut().filter_map(|score| score.get_mut("score")).for_each(upgrade);
+    }
+    if let Some(levels) = board.get_mut("levels").and_then(Value::as_object_mut) {
+        levels.values_mut().for_each(upgrade);
+    }
+}
+
+// Version 3 files held a slot for each mode of the main menu, played or
+// not, with `80x25` for the fixed window size. Sizes are now those of the
+// field, which is 76x21 in that window. Full window slots could have been
+// any size, and keep none.
+fn add_field_sizes(board: &mut Value) {
+    if let Some(scores) = board.get_mut("scores").and_then(Value::as_array_mut) {
+        scores.retain(|score| score["score"]["last"].as_u64().unwrap_or(0) > 0 || score["score"]["top"].as_array().is_some_and(|top| !top.is_empty()));
+
+        for score in scores.iter_mut().filter_map(Value::as_object_mut) {
+            if score.get("size").is_some_and(|size| *size == json!([80, 25])) {
+                score.insert("size".to_string(), json!([76, 21]));
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
 pub(crate) struct ScoreBoard {
-    scores: [Score; 4]
+    modes: BTreeMap<Mode, LBScore>,
+    levels: BTreeMap<String, LBScore>
 }
 
+// The score file.
+#[derive(Serialize, Deserialize)]
+struct StoredScoreBoard {
+    version: u64,
+    scores: Vec<Score>,
+    levels: BTreeMap<String, LBScore>
+}
+
+const MAX_GROUP_LEN: usize = "Walls Hard".len();
+
+const MAX_SIZE_LEN: usize = "full window".len();
+
+// The best game of each group, whatever the size it was played at.
 impl Renderable for ScoreBoard {
     fn render<W:Write>(&self, stdout: &mut W) {
-        let max_label_len = Difficulty::Hard.to_string().len() + " XXXxXXX".len() +1;
-        let bwstr = format!("{:max_label_len$} [last: {:>4} | best: {:>4}]", "", 0,0);
-        let margin = ((terminal_size().unwrap().0 as usize - bwstr.len()) / 2) as u16;
-
-        for (index, elem) in self.scores.iter().enumerate() {
-            let label = match elem.size {
-                Some((w,h)) => format!("{}x{}", w, h),
-                None => "Full".to_string()
+        let margin = (terminal_size().unwrap().0.saturating_sub(ScoreBoard::width()) / 2).max(1);
+        let theme = theme::current();
+        let (title, label) = (theme::palette().fg(theme.title), theme::palette().fg(theme.label));
+
+        for (index, (group, best)) in self.groups().iter().enumerate() {
+            let (score, name, size) = match best {
+                Some((mode, record)) => (record.score, record.name.as_str(), mode.size_label()),
+                None => (0, "", String::new())
             };
-            
-            let label = format!("{} {}", elem.difficulty.to_string(), label);
 
-            let str = format!("{}{:max_label_len$}{}: [{}last{}: {:>4} | {}best{}: {:>4}]",
-                color::Fg(color::Red),
+            let str = format!("{}{:MAX_GROUP_LEN$}{}: [{}best{}: {:>5} {:MAX_NAME_LEN$} {:>MAX_SIZE_LEN$}]",
+                title,
+                group,
+                theme::RESET,
                 label,
-                color::Fg(color::Reset),
-                color::Fg(color::Yellow),
-                color::Fg(color::Reset),
-                elem.score.last.to_string(),
-                color::Fg(color::Yellow),
-                color::Fg(color::Reset),
-                elem.score.best.to_string());
-                write!(stdout, "{}{}\n\r", cursor::Goto(margin, (index+1) as u16), str).unwrap();
+                theme::RESET,
+                score,
+                name,
+                size);
+            write!(stdout, "{}{}\n\r", cursor::Goto(margin, (index+1) as u16), str).unwrap();
+        }
+    }
+}
+
+// Plain text, for printing outside of the game, with the modes under the
+// rules and difficulty they share.
+impl fmt::Display for ScoreBoard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let write_table = |f: &mut fmt::Formatter, title: &str, score: &LBScore| -> fmt::Result {
+            writeln!(f, "  {:MAX_SIZE_LEN$}  last: {:>4}  best: {:>4}", title, score.last, score.best())?;
+            for (rank, record) in score.top.iter().enumerate() {
+                writeln!(f, "{}", format!("    {:>2}. {}  {}", rank + 1, record.summary(), record.details()).trim_end())?;
+            }
+            Ok(())
+        };
+
+        let mut group = None;
+        for (mode, score) in &self.modes {
+            if group.as_ref() != Some(&mode.group()) {
+                writeln!(f, "{}", mode.group())?;
+                group = Some(mode.group());
+            }
+            write_table(f, &mode.size_label(), score)?;
+        }
+
+        if !self.levels.is_empty() {
+            writeln!(f, "Levels")?;
+        }
+        for (name, score) in &self.levels {
+            write_table(f, name, score)?;
         }
+
+        Ok(())
     }
 }
 
 impl ScoreBoard {
+    // Columns taken by a row of the board.
+    pub(crate) fn width() -> u16 {
+        format!("{:MAX_GROUP_LEN$}: [best: {:>5} {:MAX_NAME_LEN$} {:>MAX_SIZE_LEN$}]", "", 0, "", "").len() as u16
+    }
+
     pub(crate) fn new() -> ScoreBoard {
         ScoreBoard {
-            scores: [
-                Score { score: LBScore { last: 0, best: 0 }, difficulty: Difficulty::Easy, size: None },
-                Score { score: LBScore { last: 0, best: 0 }, difficulty: Difficulty::Hard, size: None },
-                Score { score: LBScore { last: 0, best: 0 }, difficulty: Difficulty::Easy, size: Some((80,25)) },
-                Score { score: LBScore { last: 0, best: 0 }, difficulty: Difficulty::Hard, size: Some((80,25)) }
-            ]
+            modes: BTreeMap::new(),
+            levels: BTreeMap::new()
         }
     }
 
-    pub(crate) fn update(self, score: u64, choice: MenuAction) -> ScoreBoard {
-        let (difficulty, size) = match choice {
-            MenuAction::StartGame(difficulty, size) => (difficulty, size),
-            _ => panic!("Invalid choice")
+    // Every pair of rules and difficulty, with the best game played with
+    // them and the mode it was played in.
+    fn groups(&self) -> Vec<(String, Option<(Mode, &Record)>)> {
+        let mut groups = Vec::new();
+
+        for rules in [Rules::Wrap, Rules::Walls] {
+            for difficulty in [Difficulty::Easy, Difficulty::Hard] {
+                let best = self.modes.iter()
+                    .filter(|(mode, _)| mode.rules == rules && mode.difficulty == difficulty)
+                    .filter_map(|(mode, score)| score.top.first().map(|record| (*mode, record)))
+                    .max_by_key(|(_, record)| record.score);
+
+                groups.push((format!("{} {}", rules, difficulty), best));
+            }
+        }
+
+        groups
+    }
+
+    // Every mode played and every level with its title, modes first.
+    pub(crate) fn tables(&self) -> Vec<(String, &LBScore)> {
+        let modes = self.modes.iter().map(|(mode, score)| (mode.label(), score));
+        let levels = self.levels.iter().map(|(name, score)| (format!("Level {}", name), score));
+
+        modes.chain(levels).collect()
+    }
+
+    fn table(&self, key: ScoreKey) -> Option<&LBScore> {
+        match key {
+            ScoreKey::Mode(mode) => self.modes.get(&mode),
+            ScoreKey::Level(name) => self.levels.get(name)
+        }
+    }
+
+    // Whether a game scoring `score` would make it onto the leaderboard.
+    pub(crate) fn qualifies(&self, key: ScoreKey, score: u64) -> bool {
+        match self.table(key) {
+            Some(table) => table.qualifies(score),
+            None => LBScore::default().qualifies(score)
+        }
+    }
+
+    pub(crate) fn update(mut self, key: ScoreKey, record: Record) -> ScoreBoard {
+        let table = match key {
+            ScoreKey::Mode(mode) => self.modes.entry(mode).or_default(),
+            ScoreKey::Level(name) => self.levels.entry(name.to_string()).or_default()
         };
 
-        let mut new_scores = self.scores;
-        let elem = new_scores
-                .iter_mut()
-                .find(|c| c.difficulty == difficulty && c.size.is_some() == size.is_some()).unwrap();
+        table.add(record);
+        self
+    }
 
-        elem.score.last = score;
-        elem.score.best = std::cmp::max(elem.score.best, elem.score.last);
+    // Where scores are kept unless told otherwise.
+    pub(crate) fn default_path() -> PathBuf {
+        storage::data_dir().join(SCORES_FILE)
+    }
 
-        return ScoreBoard { scores: new_scores };
+    // The board saved in `path`, or a new one when there is no file yet.
+    pub(crate) fn load(path: &Path) -> Result<ScoreBoard, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => ScoreBoard::parse(&text).map_err(|err| err.to_string()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(ScoreBoard::new()),
+            Err(err) => Err(err.to_string())
+        }
     }
 
-    pub(crate) fn load() -> ScoreBoard {
-        let file = std::fs::File::open("scores.json");
-        if let Ok(mut file) = file {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
-            let scores: ScoreBoard = serde_json::from_str(&contents).unwrap();
-            return scores;
+    // Like `load`, but a corrupt file is moved aside to `<path>.bak` and a
+    // new board is used instead, with a message saying so. A file that
+    // cannot be read at all, or was written by a newer version, is an error.
+    pub(crate) fn load_or_recover(path: &Path) -> io::Result<(ScoreBoard, Option<String>)> {
+        let (board, warning) = storage::load_or_back_up(path, ScoreBoard::parse)?;
+        Ok((board.unwrap_or_else(ScoreBoard::new), warning.map(|warning| format!("{}, starting with no scores", warning))))
+    }
+
+    // Reads a score file of any version up to `SCHEMA_VERSION`, migrating
+    // it on the way.
+    pub(crate) fn parse(text: &str) -> Result<ScoreBoard, ParseError> {
+        let mut value: Value = serde_json::from_str(text)?;
+        if !value.is_object() {
+            return Err("not a score file".into());
+        }
+
+        let version = match value.get("version") {
+            None => 1,
+            Some(version) => version.as_u64().filter(|v| *v >= 1).ok_or(format!("invalid version {}", version))?
+        };
+        if version > SCHEMA_VERSION {
+            return Err(ParseError::Newer(version));
+        }
+
+        for from in version..SCHEMA_VERSION {
+            MIGRATIONS[from as usize - 1](&mut value);
+            value["version"] = json!(from + 1);
         }
 
-        return ScoreBoard::new();
+        let stored: StoredScoreBoard = serde_json::from_value(value)?;
+
+        Ok(ScoreBoard {
+            modes: stored.scores.into_iter().map(|elem| (elem.mode, elem.score)).collect(),
+            levels: stored.levels
+        })
+    }
+
+    pub(crate) fn level_best(&self, name: &str) -> u64 {
+        self.levels.get(name).map_or(0, LBScore::best)
     }
 
-    pub(crate) fn save(self)  {
-        let mut file = std::fs::File::create("scores.json").unwrap();    
+    fn stored(&self) -> StoredScoreBoard {
+        let scores = self.modes.iter().map(|(mode, score)| Score { score: score.clone(), mode: *mode }).collect();
+        StoredScoreBoard { version: SCHEMA_VERSION, scores, levels: self.levels.clone() }
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.stored()).unwrap()
+    }
 
-        let json = serde_json::to_string(&self).unwrap();
-        file.write_all(json.as_bytes()).unwrap();
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string(&self.stored()).unwrap();
+        storage::write_atomic(path, json.as_bytes())
     }
 
 }
\ No newline at end of file
@@ -0,0 +1,77 @@
+use std::rc::Rc;
+use crate::{
+    menu::{ Difficulty, Rules },
+    game::items::{ Item, ItemKind, ITEM_KINDS },
+    game::level::Level,
+    game::snake::Snake,
+    game::state::{ GameState, Command, Event }
+};
+
+// Drives a `GameState` without a terminal: build it with an explicit board
+// size, feed it a script of commands and inspect what happened.
+pub(crate) struct Simulation {
+    pub(crate) state: GameState,
+    pub(crate) events: Vec<Event>
+}
+
+impl Simulation {
+    // `field` is the number of playable cells, borders excluded.
+    pub(crate) fn new(difficulty: Difficulty, field: (u16, u16), seed: u64) -> Simulation {
+        Simulation::with_level(difficulty, Level::open(field), seed).with_rules(Rules::Wrap)
+    }
+
+    pub(crate) fn with_level(difficulty: Difficulty, level: Level, seed: u64) -> Simulation {
+        let rules = level.rules();
+
+        Simulation { state: GameState::new(difficulty, rules, Rc::new(level), seed), events: Vec::new() }
+    }
+
+    pub(crate) fn with_rules(mut self, rules: Rules) -> Simulation {
+        self.state.rules = rules;
+        self
+    }
+
+    pub(crate) fn with_snake(mut self, body: Vec<(u16, u16)>, dir: (i16, i16)) -> Simulation {
+        self.state.snake = Snake::new(body, dir, self.state.frame);
+        self
+    }
+
+    // Replaces whatever the board holds with the given items, looked up by
+    // name in `ITEM_KINDS`.
+    pub(crate) fn with_items(mut self, items: &[(&str, (u16, u16))]) -> Simulation {
+        let frame = self.state.frame;
+        self.state.items = items.iter()
+            .map(|(name, pos)| Item::new(kind(name), *pos, frame))
+            .collect();
+        self
+    }
+
+    pub(crate) fn step(&mut self, cmd: Command) -> Vec<Event> {
+        let events = self.state.advance(cmd);
+        self.events.extend(events.iter().cloned());
+        events
+    }
+
+    // Plays the script until it runs out or the game ends.
+    pub(crate) fn run(&mut self, script: &[Command]) -> &mut Simulation {
+        for cmd in script {
+            if self.state.game_over {
+                break;
+            }
+            self.step(*cmd);
+        }
+        self
+    }
+
+    pub(crate) fn idle(&mut self, ticks: usize) -> &mut Simulation {
+        self.run(&vec![Command::None; ticks])
+    }
+
+    pub(crate) fn head(&self) -> (u16, u16) {
+        self.state.snake.head_pos()
+    }
+}
+
+fn kind(name: &str) -> &'static ItemKind {
+    ITEM_KINDS.iter().find(|kind| kind.name == name).unwrap()
+}
@@ -1,70 +1,188 @@
-use crate::io::renderable::{ Renderable, Frame };
-use std::io::Write;
-
-use termion::{
-    color
-};
+use crate::{ io::renderable::{ Renderable, Frame }, io::theme::{ self, Theme }, game::grid::Grid };
+use std::{ collections::VecDeque, io::Write };
 
+// The body is a ring buffer with the head at the front, mirrored in an
+// occupancy grid, so that moving, growing and self-collision checks cost
+// the same whatever the snake's length.
 #[derive(Clone)]
 pub(crate) struct Snake {
-    pub(crate) body: Vec<(u16, u16)>,
+    body: VecDeque<(u16, u16)>,
+    occupied: Grid,
+    // Segments still to be added: the tail stays put while this is positive.
+    pending: usize,
     pub(crate) dir: (i16, i16),
     pub(crate) frame: Frame
 } 
 
 impl Snake {
-    pub(crate) fn mv(&self) -> Snake {
-        let mut new_x = self.body[0].0 as i16 + self.dir.0;
-        let mut new_y = self.body[0].1 as i16 + self.dir.1;
-        let mut snake = self.clone();
+    pub(crate) fn new(body: Vec<(u16, u16)>, dir: (i16, i16), frame: Frame) -> Snake {
+        let mut occupied = Grid::new(frame.field());
+        for pos in &body {
+            occupied.add(*pos);
+        }
+
+        Snake { body: VecDeque::from(body), occupied, pending: 0, dir, frame }
+    }
+
+    // Same segments on a different field; cells that fall outside of it are
+    // kept in the body but not tracked for collisions.
+    pub(crate) fn with_frame(&self, frame: Frame) -> Snake {
+        Snake { pending: self.pending, ..Snake::new(self.segments().copied().collect(), self.dir, frame) }
+    }
+
+    // Where the head would go next if the field had no borders.
+    pub(crate) fn next_head(&self) -> (i16, i16) {
+        let head = self.head_pos();
+        (head.0 as i16 + self.dir.0, head.1 as i16 + self.dir.1)
+    }
+
+    // Advances one cell, wrapping around the field. Returns true when the
+    // head lands on the snake's own body.
+    pub(crate) fn mv(&mut self) -> bool {
+        let (mut new_x, mut new_y) = self.next_head();
+        let (w, h) = self.frame.field();
 
         if new_x < 1 {
-            new_x = self.frame.field().0 as i16;
+            new_x = w as i16;
         }
         
-        if new_x > self.frame.field().0 as i16 {
+        if new_x > w as i16 {
             new_x = 1;
         }
 
         if new_y < 1 {
-            new_y = self.frame.field().1 as i16;
+            new_y = h as i16;
         }
 
-        if new_y > self.frame.field().1 as i16 {
+        if new_y > h as i16 {
             new_y = 1;
         }
 
-        snake.body.insert(0, (new_x as u16, new_y as u16));
-        snake.body.pop();
+        if self.pending > 0 {
+            self.pending -= 1;
+        } else if let Some(tail) = self.body.pop_back() {
+            self.occupied.remove(tail);
+        }
+
+        let head = (new_x as u16, new_y as u16);
+        let bitten = self.occupied.get(head);
+
+        self.occupied.add(head);
+        self.body.push_front(head);
 
-        snake
+        bitten
     }
 
     pub(crate) fn head_pos(&self) -> (u16, u16) {
         self.body[0]
     }
 
-    pub(crate) fn grow(&self, len: u16) -> Snake {
-        let mut snake = self.clone();
-        let last = self.body.len() - 1;
-        let last_pos = self.body[last].clone();
-        for _ in 0..len {
-            snake.body.push(last_pos);
+    pub(crate) fn grow(&mut self, len: u16) {
+        self.pending += len as usize;
+    }
+
+    // Cancels pending growth first, then drops segments from the tail,
+    // keeping at least `min_len` of them.
+    pub(crate) fn shrink(&mut self, len: u16, min_len: usize) {
+        let mut len = len as usize;
+        let cancelled = len.min(self.pending);
+        self.pending -= cancelled;
+        len -= cancelled;
+
+        while len > 0 && self.body.len() > min_len {
+            if let Some(tail) = self.body.pop_back() {
+                self.occupied.remove(tail);
+            }
+            len -= 1;
         }
+    }
+
+    // Length including the growth still to come.
+    pub(crate) fn len(&self) -> usize {
+        self.body.len() + self.pending
+    }
+
+    pub(crate) fn occupies(&self, pos: (u16, u16)) -> bool {
+        self.occupied.get(pos)
+    }
+
+    // From head to tail.
+    pub(crate) fn segments(&self) -> impl Iterator<Item = &(u16, u16)> {
+        self.body.iter()
+    }
+
+    // What to draw where, tail first so that the head ends up on top where
+    // a ghost snake overlaps itself.
+    pub(crate) fn glyphs(&self, theme: &Theme) -> Vec<((u16, u16), char)> {
+        let fallback = theme.snake.glyph.chars().next().unwrap_or('o');
+        let last = self.body.len() - 1;
+
+        (0..=last).rev()
+            .map(|i| {
+                let pos = self.body[i];
+                let glyph = if i == 0 {
+                    theme.head.map_or(fallback, |head| head[direction_index(self.dir)])
+                } else if i == last {
+                    theme.tail.unwrap_or(fallback)
+                } else {
+                    let ahead = toward(pos, self.body[i - 1]);
+                    let behind = toward(pos, self.body[i + 1]);
+                    theme.body.map_or(fallback, |body| body[joint_index(ahead, behind)])
+                };
+                (pos, glyph)
+            })
+            .collect()
+    }
+}
+
+// The unit step from `from` to the neighbouring segment `to`. Segments more
+// than a cell apart are neighbours across a wraparound seam, so the step
+// goes the other way.
+fn toward(from: (u16, u16), to: (u16, u16)) -> (i16, i16) {
+    let step = |a: u16, b: u16| {
+        let d = b as i16 - a as i16;
+        if d.abs() > 1 { -d.signum() } else { d }
+    };
+
+    (step(from.0, to.0), step(from.1, to.1))
+}
+
+// Index into a theme's `head`: up, down, left, right.
+fn direction_index(dir: (i16, i16)) -> usize {
+    match dir {
+        (0, -1) => 0,
+        (0, 1) => 1,
+        (-1, 0) => 2,
+        _ => 3
+    }
+}
+
+// Index into a theme's `body` for a segment whose neighbours lie in the
+// directions `a` and `b`: the four corners, then straight across and
+// straight down.
+fn joint_index(a: (i16, i16), b: (i16, i16)) -> usize {
+    let has = |dir: (i16, i16)| a == dir || b == dir;
 
-        snake
+    match (has((0, -1)), has((0, 1)), has((-1, 0)), has((1, 0))) {
+        (false, true, false, true) => 0,
+        (false, true, true, false) => 1,
+        (true, false, false, true) => 2,
+        (true, false, true, false) => 3,
+        (false, false, _, _) => 4,
+        _ => 5
     }
 }
 
 impl Renderable for Snake {
     fn render<W: Write>(&self, stdout: &mut W) {
+        let theme = theme::current();
         let mut str: String = String::new();
 
-        for (x,y) in &self.body {
-            str.push_str(&String::from(self.frame.goto(*x,*y)));
-            str.push('✿');
+        for ((x, y), glyph) in self.glyphs(theme) {
+            str.push_str(&String::from(self.frame.goto(x, y)));
+            str.push(glyph);
         }
 
-        write!(stdout, "{}{}{}", color::Fg(color::Green), str, color::Fg(color::Reset)).unwrap();
+        write!(stdout, "{}{}{}", theme::palette().fg(theme.snake.color), str, theme::RESET).unwrap();
     }
 }
\ No newline at end of file
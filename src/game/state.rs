@@ -0,0 +1,376 @@
+use std::{ fmt, rc::Rc };
+use serde::{ Serialize, Deserialize };
+use rand::{ rngs::StdRng, seq::SliceRandom, Rng, SeedableRng };
+use crate::{
+    menu::{ Difficulty, Rules },
+    io::renderable::Frame,
+    game::app,
+    game::items::{ self, Item, ItemKind, Effect, ActiveEffect, ITEM_KINDS },
+    game::level::Level,
+    game::snake::Snake
+};
+
+// Directions are as seen on screen: `Up` moves the snake towards the top.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Command {
+    Up,
+    Down,
+    Left,
+    Right,
+    Quit,
+    Pause,
+    // Opens the key bindings screen.
+    Rebind,
+    // Opens the help screen.
+    Help,
+    // The terminal window lost focus.
+    FocusLost,
+    None
+}
+
+impl Command {
+    pub(crate) fn dir(&self) -> Option<(i16, i16)> {
+        match self {
+            Command::Up     => Some((0,-1)),
+            Command::Down   => Some((0,1)),
+            Command::Left   => Some((-1,0)),
+            Command::Right  => Some((1,0)),
+            Command::Quit | Command::Pause | Command::Rebind | Command::Help | Command::FocusLost | Command::None => None
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Event {
+    ItemEaten(&'static str),
+    ItemExpired(&'static str),
+    GameOver,
+    Won,
+    QuitRequested
+}
+
+// How a game came to an end.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub(crate) enum Ending {
+    // Ran into the border, with the walls rules.
+    Border,
+    // Ran into a wall of the level.
+    Wall,
+    Itself,
+    Quit,
+    Won
+}
+
+impl fmt::Display for Ending {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ending::Border => write!(f, "border"),
+            Ending::Wall => write!(f, "wall"),
+            Ending::Itself => write!(f, "itself"),
+            Ending::Quit => write!(f, "quit"),
+            Ending::Won => write!(f, "won")
+        }
+    }
+}
+
+pub(crate) const START_SPEED: u64 = 10;
+
+// How many items the board holds when there is room for them.
+pub(crate) const ITEMS_ON_BOARD: usize = 3;
+
+// Chance per tick, one in this many, that a bonus item shows up while
+// none is on the board.
+const BONUS_ODDS: u32 = 100;
+
+// Random probes tried before falling back to scanning the whole field for
+// a free cell. Only nearly full boards get past them.
+const SPAWN_PROBES: usize = 16;
+
+// The whole game as a plain value: no terminal, no clock, no global RNG.
+// Given the same seed and the same sequence of commands, `step` always
+// produces the same sequence of states.
+#[derive(Clone)]
+pub(crate) struct GameState {
+    pub(crate) frame: Frame,
+    // Regular items, fewer than `ITEMS_ON_BOARD` only while there are not
+    // enough free cells, plus at most one bonus item.
+    pub(crate) items: Vec<Item>,
+    pub(crate) effects: Vec<ActiveEffect>,
+    pub(crate) snake: Snake,
+    pub(crate) speed: u64,
+    pub(crate) score: u64,
+    pub(crate) game_over: bool,
+    pub(crate) won: bool,
+    // What ended the game, once it is over.
+    pub(crate) ending: Option<Ending>,
+    pub(crate) difficulty: Difficulty,
+    pub(crate) rules: Rules,
+    pub(crate) level: Rc<Level>,
+    rng: StdRng
+}
+
+// -------------- GameState impl --------------
+
+impl GameState {
+    pub(crate) fn new(difficulty: Difficulty, rules: Rules, level: Rc<Level>, seed: u64) -> GameState {
+        let frame = app::frame_for(level.size);
+
+        let mut result = GameState {
+            frame,
+            items: Vec::new(),
+            effects: Vec::new(),
+            snake: Snake::new(level.start.clone(), level.dir, frame),
+            speed: START_SPEED,
+            score: 0,
+            game_over: false,
+            won: false,
+            ending: None,
+            difficulty,
+            rules,
+            level,
+            rng: StdRng::seed_from_u64(seed)
+        };
+
+        result.refill_items();
+        result
+    }
+
+    // Only open fields follow the terminal size; levels keep the size they
+    // were drawn with. The field never shrinks past the snake, since there
+    // is no fair way to fold it into a smaller one: the board stays larger
+    // than the window instead, and the game waits until it fits again. Items
+    // stay where they are unless they end up outside the field.
+    pub(crate) fn resize(&self, frame: Frame) -> GameState {
+        if frame == self.frame {
+            return self.clone();
+        }
+
+        let (snake_w, snake_h) = self.snake.segments()
+            .fold((1, 1), |(w, h), &(x, y)| (w.max(x), h.max(y)));
+        let frame = Frame::new(frame.pos, (frame.size.0.max(snake_w + 2), frame.size.1.max(snake_h + 2)));
+
+        let mut result = self.clone();
+        result.frame = frame;
+        result.level = Rc::new(Level::open(frame.field()));
+        result.snake = self.snake.with_frame(frame);
+
+        result.items.retain(|item| frame.in_field((item.pos.0 as i16, item.pos.1 as i16)));
+        for item in result.items.iter_mut() {
+            item.frame = frame;
+        }
+        result.refill_items();
+
+        result
+    }
+
+    fn is_free(&self, pos: (u16, u16)) -> bool {
+        !self.snake.occupies(pos) && !self.level.is_wall(pos) && self.items.iter().all(|item| item.pos != pos)
+    }
+
+    // Cells an item may be placed on: neither wall, snake nor item, and
+    // inside the level's apple zones when it has any.
+    fn free_cells(&self) -> Vec<(u16, u16)> {
+        let candidates: Vec<(u16, u16)> = if !self.level.apple_zones.is_empty() {
+            self.level.apple_zones.clone()
+        } else {
+            let (w, h) = self.frame.field();
+            (1..=h).flat_map(|y| (1..=w).map(move |x| (x, y))).collect()
+        };
+
+        candidates.into_iter()
+            .filter(|pos| self.is_free(*pos))
+            .collect()
+    }
+
+    fn random_free_cell(&mut self) -> Option<(u16, u16)> {
+        let (w, h) = self.frame.field();
+
+        for _ in 0..SPAWN_PROBES {
+            let pos = match self.level.apple_zones.choose(&mut self.rng) {
+                Some(pos) => *pos,
+                None => (self.rng.gen_range(1..=w), self.rng.gen_range(1..=h))
+            };
+
+            if self.is_free(pos) {
+                return Some(pos);
+            }
+        }
+
+        self.free_cells().choose(&mut self.rng).copied()
+    }
+
+    fn playable_cells(&self) -> usize {
+        let (w, h) = self.frame.field();
+        w as usize * h as usize - self.level.walls.len()
+    }
+
+    fn spawn_item(&mut self, bonus: bool) -> Option<Item> {
+        let kinds: Vec<&'static ItemKind> = ITEM_KINDS.iter()
+            .filter(|kind| kind.lifetime.is_some() == bonus)
+            .collect();
+        let kind = *kinds.choose_weighted(&mut self.rng, |kind| kind.weight).unwrap();
+
+        self.random_free_cell().map(|pos| Item::new(kind, pos, self.frame))
+    }
+
+    pub(crate) fn bonus(&self) -> Option<&Item> {
+        self.items.iter().find(|item| item.ttl.is_some())
+    }
+
+    fn refill_items(&mut self) {
+        while self.items.iter().filter(|item| item.ttl.is_none()).count() < ITEMS_ON_BOARD {
+            match self.spawn_item(false) {
+                Some(item) => self.items.push(item),
+                None => break
+            }
+        }
+    }
+
+    fn is_active(&self, effect: fn(&Effect) -> bool) -> bool {
+        self.effects.iter().any(|active| effect(&active.effect))
+    }
+
+    fn multiplier(&self) -> u64 {
+        self.effects.iter()
+            .map(|active| match active.effect {
+                Effect::Multiplier(factor, _) => factor,
+                _ => 1
+            })
+            .product()
+    }
+
+    fn apply(&mut self, item: &Item) {
+        let kind = item.kind;
+
+        self.speed += kind.speed_up;
+        self.score += item.worth() * self.multiplier();
+
+        match kind.effect {
+            Effect::Grow(len) => self.snake.grow(len),
+            Effect::Shrink(len) => self.snake.shrink(len, items::MIN_LENGTH),
+            Effect::SlowDown(amount) => self.speed = self.speed.saturating_sub(amount).max(1),
+            Effect::Ghost(ticks) | Effect::Multiplier(_, ticks) => {
+                // Picking up an effect that is still running restarts it.
+                let same = std::mem::discriminant(&kind.effect);
+                self.effects.retain(|active| std::mem::discriminant(&active.effect) != same);
+                self.effects.push(ActiveEffect { effect: kind.effect, remaining: ticks });
+            }
+        }
+    }
+
+    // Counts down bonus items, drops those that ran out and now and then
+    // puts a new one on the board.
+    fn age_bonus(&mut self, events: &mut Vec<Event>) {
+        for item in self.items.iter_mut() {
+            if let Some(ttl) = item.ttl.as_mut() {
+                *ttl -= 1;
+                if *ttl == 0 {
+                    events.push(Event::ItemExpired(item.kind.name));
+                }
+            }
+        }
+
+        self.items.retain(|item| item.ttl != Some(0));
+
+        if self.bonus().is_none() && self.rng.gen_ratio(1, BONUS_ODDS) {
+            if let Some(item) = self.spawn_item(true) {
+                self.items.push(item);
+            }
+        }
+    }
+
+    fn expire_effects(&mut self) {
+        for active in self.effects.iter_mut() {
+            active.remaining -= 1;
+        }
+
+        self.effects.retain(|active| active.remaining > 0);
+    }
+
+    pub(crate) fn step(mut self, cmd: Command) -> (GameState, Vec<Event>) {
+        let events = self.advance(cmd);
+        (self, events)
+    }
+
+    // `step` in place: plays one tick and reports what happened.
+    pub(crate) fn advance(&mut self, cmd: Command) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        if cmd == Command::Quit {
+            events.push(Event::QuitRequested);
+        }
+
+        self.react_to_command(cmd);
+
+        if self.rules == Rules::Walls && !self.frame.in_field(self.snake.next_head()) {
+            self.game_over = true;
+            self.ending = Some(Ending::Border);
+            events.push(Event::GameOver);
+            return events;
+        }
+
+        let bitten = self.snake.mv();
+        self.expire_effects();
+        self.check_collision(bitten, &mut events);
+
+        if !self.game_over {
+            self.age_bonus(&mut events);
+        }
+
+        events
+    }
+
+    // Whether `cmd` would change the snake's direction on the next tick.
+    // Hard mode lets the snake reverse, straight into its own neck.
+    pub(crate) fn turns(&self, cmd: Command) -> bool {
+        let dir = self.snake.dir;
+
+        cmd.dir().is_some_and(|newdir| {
+            newdir != dir && (self.difficulty == Difficulty::Hard || newdir != (-dir.0, -dir.1))
+        })
+    }
+
+    fn react_to_command(&mut self, cmd: Command) {
+        if let Some(newdir) = cmd.dir().filter(|_| self.turns(cmd)) {
+            self.snake.dir = newdir;
+        }
+    }
+
+    fn check_collision(&mut self, bitten: bool, events: &mut Vec<Event>) {
+        let head_pos = self.snake.head_pos();
+        let ghost = self.is_active(|effect| matches!(effect, Effect::Ghost(_)));
+
+        if (bitten && !ghost) || self.level.is_wall(head_pos) {
+            self.game_over = true;
+            self.ending = Some(if self.level.is_wall(head_pos) { Ending::Wall } else { Ending::Itself });
+            events.push(Event::GameOver);
+            return;
+        }
+
+        let eaten = self.items.iter().position(|item| item.pos == head_pos);
+
+        if let Some(index) = eaten {
+            let item = self.items.remove(index);
+            self.apply(&item);
+            events.push(Event::ItemEaten(item.kind.name));
+        }
+
+        // Counting pending growth, the snake covers every cell that is not a
+        // wall: there is nowhere left to go.
+        if self.snake.len() >= self.playable_cells() {
+            self.won = true;
+            self.game_over = true;
+            self.ending = Some(Ending::Won);
+            events.push(Event::Won);
+            return;
+        }
+
+        // An eaten item comes back elsewhere. On levels with apple zones
+        // missing items are retried every tick, since the snake may since
+        // have moved out of the zones; on open fields free cells only ever
+        // run out.
+        if eaten.is_some() || !self.level.apple_zones.is_empty() {
+            self.refill_items();
+        }
+    }
+}
@@ -0,0 +1,207 @@
+// Lifetime statistics: what all the games played add up to, overall and
+// for each rules and difficulty or level. They are kept in `stats.json`,
+// next to the score file.
+
+use std::{ collections::BTreeMap, io, path::{ Path, PathBuf } };
+use serde::{ Serialize, Deserialize };
+use serde_json::Value;
+use crate::{
+    game::scores::{ Record, ScoreKey },
+    game::state::Ending,
+    io::storage::{ self, ParseError }
+};
+
+pub(crate) const STATS_FILE: &str = "stats.json";
+
+// The version of the statistics file written by this build.
+const STATS_VERSION: u64 = 1;
+
+// How many of the latest scores are kept for the charts.
+pub(crate) const HISTORY_LEN: usize = 60;
+
+#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+pub(crate) struct Totals {
+    pub(crate) games: u32,
+    // Seconds played, pauses left out.
+    pub(crate) duration: u64,
+    pub(crate) score: u64,
+    // The longest the snake was at the end of a game.
+    pub(crate) longest: usize,
+    // How many of each item kind were eaten, by name.
+    pub(crate) eaten: BTreeMap<String, u32>,
+    pub(crate) endings: BTreeMap<Ending, u32>,
+    // The scores of the latest games, oldest first.
+    pub(crate) history: Vec<u64>
+}
+
+impl Totals {
+    fn add(&mut self, record: &Record, ending: Ending) {
+        self.games += 1;
+        self.duration += record.duration;
+        self.score += record.score;
+        self.longest = self.longest.max(record.length);
+
+        for (item, count) in &record.eaten {
+            *self.eaten.entry(item.clone()).or_insert(0) += count;
+        }
+        *self.endings.entry(ending).or_insert(0) += 1;
+
+        self.history.push(record.score);
+        if self.history.len() > HISTORY_LEN {
+            self.history.drain(..self.history.len() - HISTORY_LEN);
+        }
+    }
+
+    // Rounded to the nearest point.
+    pub(crate) fn average(&self) -> u64 {
+        match self.games {
+            0 => 0,
+            games => (self.score + games as u64 / 2) / games as u64
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub(crate) struct Stats {
+    pub(crate) overall: Totals,
+    groups: BTreeMap<String, Totals>
+}
+
+// The statistics file.
+#[derive(Serialize, Deserialize)]
+struct StoredStats {
+    version: u64,
+    overall: Totals,
+    groups: BTreeMap<String, Totals>
+}
+
+impl Stats {
+    // The statistics file that goes with the score file in `scores`.
+    pub(crate) fn path_for(scores: &Path) -> PathBuf {
+        scores.with_file_name(STATS_FILE)
+    }
+
+    pub(crate) fn record(&mut self, key: ScoreKey, record: &Record, ending: Ending) {
+        self.overall.add(record, ending);
+        self.groups.entry(key.group()).or_default().add(record, ending);
+    }
+
+    // The overall totals first, then those of every group played.
+    pub(crate) fn tables(&self) -> Vec<(String, &Totals)> {
+        let groups = self.groups.iter().map(|(group, totals)| (group.clone(), totals));
+        std::iter::once(("Overall".to_string(), &self.overall)).chain(groups).collect()
+    }
+
+    // The statistics saved in `path`, or none when there is no file yet. A
+    // corrupt file is moved aside to `<path>.bak`, as with the score file.
+    pub(crate) fn load_or_recover(path: &Path) -> io::Result<(Stats, Option<String>)> {
+        let (stats, warning) = storage::load_or_back_up(path, Stats::parse)?;
+        Ok((stats.unwrap_or_default(), warning.map(|warning| format!("{}, starting with no statistics", warning))))
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<Stats, ParseError> {
+        let value: Value = serde_json::from_str(text)?;
+        let version = value.get("version").and_then(Value::as_u64).ok_or("not a statistics file")?;
+        if version == 0 {
+            return Err(format!("invalid version {}", version).into());
+        }
+        if version > STATS_VERSION {
+            return Err(ParseError::Newer(version));
+        }
+
+        let stored: StoredStats = serde_json::from_value(value)?;
+
+        Ok(Stats { overall: stored.overall, groups: stored.groups })
+    }
+
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        let stored = StoredStats { version: STATS_VERSION, overall: self.overall.clone(), groups: self.groups.clone() };
+        storage::write_atomic(path, serde_json::to_string(&stored).unwrap().as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ env, fs, process };
+    use crate::game::scores::Mode;
+    use crate::menu::{ Difficulty, Rules };
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("snake-{}-{}", process::id(), name))
+    }
+
+    fn played(score: u64, length: usize, eaten: &[(&str, u32)]) -> Record {
+        Record {
+            score,
+            length,
+            duration: 30,
+            eaten: eaten.iter().map(|(item, count)| (item.to_string(), *count)).collect(),
+            ..Record::default()
+        }
+    }
+
+    #[test]
+    fn statistics_add_up_overall_and_per_group() {
+        let easy = ScoreKey::Mode(Mode { rules: Rules::Wrap, difficulty: Difficulty::Easy, size: Some((60, 20)) });
+        let other_size = ScoreKey::Mode(Mode { size: None, ..match easy { ScoreKey::Mode(mode) => mode, _ => unreachable!() } });
+        let mut stats = Stats::default();
+
+        stats.record(easy, &played(10, 8, &[("Red apple", 4)]), Ending::Itself);
+        stats.record(other_size, &played(5, 12, &[("Red apple", 1), ("Gem", 1)]), Ending::Quit);
+        stats.record(ScoreKey::Level("Garden"), &played(0, 3, &[]), Ending::Wall);
+
+        let overall = &stats.overall;
+        assert_eq!((overall.games, overall.duration, overall.longest), (3, 90, 12));
+        assert_eq!(overall.average(), 5);
+        assert_eq!(overall.eaten["Red apple"], 5);
+        assert_eq!(overall.endings[&Ending::Wall], 1);
+        assert_eq!(overall.history, vec![10, 5, 0]);
+
+        let tables = stats.tables();
+        let titles: Vec<&str> = tables.iter().map(|(title, _)| title.as_str()).collect();
+        assert_eq!(titles, vec!["Overall", "Level Garden", "Wrap Easy"]);
+        assert_eq!(tables[2].1.games, 2);
+    }
+
+    #[test]
+    fn statistics_keep_the_latest_scores() {
+        let mut stats = Stats::default();
+        for score in 0..HISTORY_LEN as u64 + 5 {
+            stats.record(ScoreKey::Level("Garden"), &played(score, 3, &[]), Ending::Border);
+        }
+
+        assert_eq!(stats.overall.history.len(), HISTORY_LEN);
+        assert_eq!(stats.overall.history[0], 5);
+        assert_eq!(stats.overall.games, HISTORY_LEN as u32 + 5);
+    }
+
+    #[test]
+    fn statistics_are_saved_next_to_the_scores() {
+        let dir = temp_path("stats");
+        let path = Stats::path_for(&dir.join("scores.json"));
+        assert_eq!(path, dir.join("stats.json"));
+
+        let mut stats = Stats::default();
+        stats.record(ScoreKey::Level("Garden"), &played(7, 4, &[("Star", 2)]), Ending::Won);
+        stats.save(&path).unwrap();
+
+        assert_eq!(Stats::load_or_recover(&path).unwrap(), (stats, None));
+        assert_eq!(Stats::load_or_recover(&dir.join("missing.json")).unwrap(), (Stats::default(), None));
+
+        // A newer game still needs the file, so it is left where it is.
+        let newer = r#"{"version":99,"overall":{},"groups":{}}"#;
+        fs::write(&path, newer).unwrap();
+        assert!(Stats::load_or_recover(&path).unwrap_err().to_string().contains("newer version"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+        assert!(!dir.join("stats.json.bak").exists());
+
+        fs::write(&path, r#"{"version":0,"overall":{},"groups":{}}"#).unwrap();
+        let (recovered, warning) = Stats::load_or_recover(&path).unwrap();
+        assert_eq!(recovered, Stats::default());
+        assert!(warning.unwrap().contains("invalid version"));
+        assert!(dir.join("stats.json.bak").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,779 @@
+use std::{ env, fs, path::PathBuf, process, time::{ Duration, Instant } };
+use crate::{
+    menu::{ Difficulty, Rules, GameOptions },
+    game::app::{ App, window_for },
+    game::level::Level,
+    game::scores::{ ScoreBoard, ScoreKey, Mode, Record, LEADERBOARD_SIZE },
+    io::renderable::Frame,
+    io::screen::Screen,
+    io::theme::Theme,
+    io::storage::ParseError,
+    game::scheduler::{ tick_interval, Scheduler },
+    game::sim::Simulation,
+    game::state::{ Command, Event, Ending }
+};
+
+const FIELD: (u16, u16) = (10, 8);
+const SEED: u64 = 42;
+
+// Items parked where none of the scripted moves below can reach them.
+fn sim(difficulty: Difficulty) -> Simulation {
+    Simulation::new(difficulty, FIELD, SEED).with_items(&[("Red apple", (9,7)), ("Yellow apple", (10,8))])
+}
+
+// -------------- wraparound --------------
+
+#[test]
+fn wraps_around_right_edge() {
+    let mut sim = sim(Difficulty::Easy).with_snake(vec![(10,4),(9,4),(8,4)], (1,0));
+    sim.idle(1);
+    assert_eq!(sim.head(), (1,4));
+}
+
+#[test]
+fn wraps_around_left_edge() {
+    let mut sim = sim(Difficulty::Easy).with_snake(vec![(1,4),(2,4),(3,4)], (-1,0));
+    sim.idle(1);
+    assert_eq!(sim.head(), (10,4));
+}
+
+#[test]
+fn wraps_around_bottom_edge() {
+    let mut sim = sim(Difficulty::Easy).with_snake(vec![(5,8),(5,7),(5,6)], (0,1));
+    sim.idle(1);
+    assert_eq!(sim.head(), (5,1));
+}
+
+#[test]
+fn wraps_around_top_edge() {
+    let mut sim = sim(Difficulty::Easy).with_snake(vec![(5,1),(5,2),(5,3)], (0,-1));
+    sim.idle(1);
+    assert_eq!(sim.head(), (5,8));
+}
+
+#[test]
+fn full_lap_returns_to_start() {
+    let mut sim = sim(Difficulty::Easy).with_snake(vec![(4,2),(3,2),(2,2)], (1,0));
+    sim.idle(FIELD.0 as usize);
+    assert_eq!(sim.head(), (4,2));
+    assert!(!sim.state.game_over);
+}
+
+// -------------- walls --------------
+
+#[test]
+fn walls_end_the_game_at_every_edge() {
+    let cases = [
+        (vec![(10,4),(9,4),(8,4)], (1,0)),
+        (vec![(1,4),(2,4),(3,4)], (-1,0)),
+        (vec![(5,8),(5,7),(5,6)], (0,1)),
+        (vec![(5,1),(5,2),(5,3)], (0,-1))
+    ];
+
+    for (body, dir) in cases {
+        let head = body[0];
+        let mut sim = sim(Difficulty::Easy).with_rules(Rules::Walls).with_snake(body, dir);
+        let events = sim.step(Command::None);
+
+        assert_eq!(events, vec![Event::GameOver]);
+        assert_eq!(sim.state.ending, Some(Ending::Border));
+        assert_eq!(sim.head(), head);
+    }
+}
+
+#[test]
+fn walls_allow_moving_along_the_border() {
+    let mut sim = sim(Difficulty::Easy).with_rules(Rules::Walls).with_snake(vec![(1,1),(1,2),(1,3)], (0,-1));
+    sim.step(Command::Right);
+    sim.idle(8);
+
+    assert_eq!(sim.head(), (10,1));
+    assert!(!sim.state.game_over);
+}
+
+// -------------- levels --------------
+
+const LEVEL: &str = "name: Test
+rules: wrap
+---
+......
+.#....
+..v...
+......
+..++..
+";
+
+#[test]
+fn builtin_levels_parse() {
+    assert!(!Level::builtin().is_empty());
+}
+
+#[test]
+fn level_sets_start_walls_and_rules() {
+    let level = Level::parse(LEVEL).unwrap();
+
+    assert_eq!(level.name, "Test");
+    assert_eq!(level.size, (6,5));
+    assert_eq!(level.rules(), Rules::Wrap);
+    assert!(level.is_wall((2,2)));
+    assert_eq!(level.start, vec![(3,3),(3,2),(3,1)]);
+    assert_eq!(level.dir, (0,1));
+}
+
+#[test]
+fn levels_without_rules_have_walls() {
+    let level = Level::parse("name: x\n---\n..>...\n").unwrap();
+    assert_eq!(level.rules(), Rules::Walls);
+    assert_eq!(Simulation::with_level(Difficulty::Easy, level, SEED).state.rules, Rules::Walls);
+}
+
+#[test]
+fn level_rejects_bad_maps() {
+    assert!(Level::parse("name: x\n---\n......\n").is_err());
+    assert!(Level::parse("name: x\n---\n>>....\n").is_err());
+    assert!(Level::parse("name: x\n---\n.>....\n").is_err());
+    assert!(Level::parse("name: x\n---\n..>.?.\n").is_err());
+    assert!(Level::parse("---\n..>...\n").is_err());
+}
+
+#[test]
+fn apples_spawn_only_in_apple_zones() {
+    for seed in 0..20 {
+        let sim = Simulation::with_level(Difficulty::Easy, Level::parse(LEVEL).unwrap(), seed);
+
+        for item in &sim.state.items {
+            assert!(sim.state.level.apple_zones.contains(&item.pos));
+        }
+    }
+}
+
+#[test]
+fn running_into_a_level_wall_ends_the_game() {
+    let mut sim = Simulation::with_level(Difficulty::Easy, Level::parse(LEVEL).unwrap(), SEED);
+    sim.step(Command::Left);
+
+    assert_eq!(sim.head(), (2,3));
+    assert!(!sim.state.game_over);
+
+    sim.step(Command::Up);
+
+    assert_eq!(sim.head(), (2,2));
+    assert!(sim.state.game_over);
+    assert_eq!(sim.state.ending, Some(Ending::Wall));
+}
+
+// -------------- items --------------
+
+// Eats the named item placed right in front of the snake's head, then
+// clears the board so that the following ticks are not disturbed.
+fn eat(sim: Simulation, name: &str) -> (Simulation, Vec<Event>) {
+    let mut sim = sim.with_items(&[(name, (4,1))]);
+    let events = sim.step(Command::None);
+    sim.state.items.clear();
+    (sim, events)
+}
+
+#[test]
+fn red_apple_grows_by_one() {
+    let (mut sim, events) = eat(sim(Difficulty::Easy), "Red apple");
+
+    assert_eq!(events, vec![Event::ItemEaten("Red apple")]);
+    assert_eq!(sim.state.snake.len(), 4);
+    assert_eq!(sim.state.score, 1);
+    assert_eq!(sim.state.speed, 11);
+
+    sim.idle(3);
+    assert_eq!(sim.state.snake.len(), 4);
+}
+
+#[test]
+fn yellow_apple_grows_by_two() {
+    let (mut sim, events) = eat(sim(Difficulty::Easy), "Yellow apple");
+
+    assert_eq!(events, vec![Event::ItemEaten("Yellow apple")]);
+    assert_eq!(sim.state.snake.len(), 5);
+    assert_eq!(sim.state.score, 2);
+    assert_eq!(sim.state.speed, 12);
+
+    sim.idle(3);
+    assert_eq!(sim.state.snake.len(), 5);
+}
+
+#[test]
+fn eaten_item_is_replaced() {
+    let mut sim = sim(Difficulty::Easy).with_items(&[("Red apple", (4,1))]);
+    sim.step(Command::None);
+
+    assert_eq!(sim.state.items.iter().filter(|item| item.ttl.is_none()).count(), 3);
+}
+
+#[test]
+fn bonus_items_vanish_after_their_lifetime() {
+    let mut sim = sim(Difficulty::Easy).with_items(&[("Diamond", (9,7))]);
+    sim.idle(59);
+    assert!(sim.state.bonus().is_some());
+
+    let events = sim.step(Command::None);
+    assert!(events.contains(&Event::ItemExpired("Diamond")));
+    assert!(sim.state.items.iter().all(|item| item.kind.name != "Diamond"));
+}
+
+#[test]
+fn bonus_items_are_worth_more_when_reached_quickly() {
+    let fresh = sim(Difficulty::Easy).with_items(&[("Diamond", (4,1))]);
+    let (fresh, _) = eat(fresh, "Diamond");
+
+    let mut late = sim(Difficulty::Easy).with_items(&[("Diamond", (4,1))]);
+    late.state.items[0].ttl = Some(30);
+    late.step(Command::None);
+
+    assert_eq!(fresh.state.score, 10);
+    assert_eq!(late.state.score, 5);
+}
+
+#[test]
+fn scissors_shrink_but_keep_a_minimum_length() {
+    let body = vec![(3,1),(2,1),(1,1),(1,2),(1,3),(1,4)];
+    let (sim, _) = eat(sim(Difficulty::Easy).with_snake(body, (1,0)), "Scissors");
+    assert_eq!(sim.state.snake.len(), 3);
+
+    let (sim, _) = eat(sim.with_snake(vec![(3,1),(2,1),(1,1)], (1,0)), "Scissors");
+    assert_eq!(sim.state.snake.len(), 2);
+}
+
+#[test]
+fn snowflake_slows_down() {
+    let (sim, _) = eat(sim(Difficulty::Easy), "Snowflake");
+    assert_eq!(sim.state.speed, 7);
+}
+
+#[test]
+fn ghost_passes_through_own_body_until_it_expires() {
+    let (mut sim, _) = eat(sim(Difficulty::Hard), "Ghost");
+    assert_eq!(sim.state.effects.len(), 1);
+
+    sim.step(Command::Left);
+    assert!(!sim.state.game_over);
+
+    sim.idle(100);
+    assert!(sim.state.effects.is_empty());
+
+    sim.step(Command::Right);
+    assert!(sim.state.game_over);
+}
+
+#[test]
+fn star_multiplies_points_for_a_while() {
+    let (sim, _) = eat(sim(Difficulty::Easy), "Star");
+    assert_eq!(sim.state.score, 1);
+
+    let (mut sim, _) = eat(sim.with_snake(vec![(3,1),(2,1),(1,1)], (1,0)), "Yellow apple");
+    assert_eq!(sim.state.score, 5);
+
+    sim.idle(100);
+    let before = sim.state.score;
+    let (sim, _) = eat(sim.with_snake(vec![(3,1),(2,1),(1,1)], (1,0)), "Yellow apple");
+    assert_eq!(sim.state.score - before, 2);
+}
+
+// -------------- collisions --------------
+
+#[test]
+fn running_into_own_body_ends_the_game() {
+    let body = vec![(2,2),(3,2),(3,3),(2,3),(1,3)];
+    let mut sim = sim(Difficulty::Easy).with_snake(body, (-1,0));
+    let events = sim.step(Command::Down);
+
+    assert_eq!(sim.head(), (2,3));
+    assert!(sim.state.game_over);
+    assert_eq!(sim.state.ending, Some(Ending::Itself));
+    assert_eq!(events, vec![Event::GameOver]);
+}
+
+#[test]
+fn moving_into_the_cell_the_tail_leaves_is_safe() {
+    let body = vec![(2,2),(3,2),(3,3),(2,3)];
+    let mut sim = sim(Difficulty::Easy).with_snake(body, (-1,0));
+    sim.step(Command::Down);
+
+    assert_eq!(sim.head(), (2,3));
+    assert!(!sim.state.game_over);
+}
+
+#[test]
+fn easy_mode_ignores_reversal() {
+    let mut sim = sim(Difficulty::Easy);
+    sim.step(Command::Left);
+
+    assert_eq!(sim.head(), (4,1));
+    assert_eq!(sim.state.snake.dir, (1,0));
+    assert!(!sim.state.game_over);
+}
+
+#[test]
+fn hard_mode_allows_reversal_into_own_body() {
+    let mut sim = sim(Difficulty::Hard);
+    sim.step(Command::Left);
+
+    assert_eq!(sim.head(), (2,1));
+    assert!(sim.state.game_over);
+}
+
+#[test]
+fn both_modes_allow_perpendicular_turns() {
+    for difficulty in [Difficulty::Easy, Difficulty::Hard] {
+        let mut sim = sim(difficulty);
+        sim.step(Command::Down);
+
+        assert_eq!(sim.head(), (3,2));
+        assert!(!sim.state.game_over);
+    }
+}
+
+// -------------- spawning and winning --------------
+
+#[test]
+fn items_never_spawn_on_the_snake_or_each_other() {
+    for seed in 0..50 {
+        let mut sim = Simulation::new(Difficulty::Easy, (5,1), seed);
+        let items: Vec<(u16, u16)> = sim.state.items.iter().map(|item| item.pos).collect();
+
+        assert_eq!(items.len(), 2);
+        assert_ne!(items[0], items[1]);
+        assert!(items.iter().all(|pos| !sim.state.snake.occupies(*pos)));
+
+        sim.idle(1);
+        assert!(sim.state.items.iter().all(|item| !sim.state.snake.occupies(item.pos)));
+    }
+}
+
+#[test]
+fn items_are_left_out_when_the_board_has_no_room() {
+    let sim = Simulation::new(Difficulty::Easy, (4,1), SEED);
+    let items: Vec<(u16, u16)> = sim.state.items.iter().map(|item| item.pos).collect();
+
+    assert_eq!(items, vec![(4,1)]);
+}
+
+#[test]
+fn filling_the_board_wins_the_game() {
+    let mut sim = Simulation::new(Difficulty::Easy, (4,1), SEED).with_items(&[("Red apple", (4,1))]);
+    let events = sim.step(Command::None);
+
+    assert_eq!(events, vec![Event::ItemEaten("Red apple"), Event::Won]);
+    assert!(sim.state.won);
+    assert!(sim.state.game_over);
+}
+
+// -------------- resizing --------------
+
+// The frame a terminal would give a field of the given size.
+fn frame(field: (u16, u16)) -> Frame {
+    Frame::new((1,1), (field.0 + 2, field.1 + 2))
+}
+
+#[test]
+fn resize_keeps_items_that_still_fit() {
+    let sim = Simulation::new(Difficulty::Easy, FIELD, SEED).with_items(&[("Red apple", (4,4)), ("Yellow apple", (6,2)), ("Red apple", (9,7))]);
+    let state = sim.state.resize(frame((8,6)));
+
+    let items: Vec<(u16, u16)> = state.items.iter().map(|item| item.pos).collect();
+    assert_eq!(&items[..2], &[(4,4), (6,2)]);
+    assert_eq!(items.len(), 3);
+    assert!(state.items.iter().all(|item| item.frame == state.frame && state.frame.in_field((item.pos.0 as i16, item.pos.1 as i16))));
+}
+
+#[test]
+fn field_does_not_shrink_past_the_snake() {
+    let sim = sim(Difficulty::Easy).with_snake(vec![(9,5),(8,5),(7,5)], (1,0));
+    let state = sim.state.resize(frame((6,3)));
+
+    assert_eq!(state.frame.field(), (9,5));
+    assert!(state.snake.segments().all(|&pos| state.snake.occupies(pos)));
+}
+
+// -------------- scheduler --------------
+
+#[test]
+fn tick_interval_keeps_sub_millisecond_precision() {
+    assert_eq!(tick_interval(10, false), Duration::from_millis(100));
+    assert_eq!(tick_interval(2000, false), Duration::from_micros(500));
+    assert!(tick_interval(2000, true) > tick_interval(2000, false));
+}
+
+// Polls every `poll` for `total`, the way the game loop does, and counts
+// the ticks played.
+fn count_ticks(scheduler: &mut Scheduler, start: Instant, poll: Duration, total: Duration, interval: Duration) -> u32 {
+    let mut ticks = 0;
+    let mut elapsed = Duration::ZERO;
+
+    while elapsed <= total {
+        while scheduler.due(start + elapsed, interval) {
+            ticks += 1;
+        }
+        elapsed += poll;
+    }
+
+    ticks
+}
+
+#[test]
+fn scheduler_does_not_drift_with_late_polls() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(start);
+    let ticks = count_ticks(&mut scheduler, start, Duration::from_millis(7), Duration::from_secs(10), Duration::from_millis(100));
+
+    // Due at 0, 100, ..., 9900ms; the last poll happens at 9996ms.
+    assert_eq!(ticks, 100);
+}
+
+#[test]
+fn scheduler_does_not_drop_whole_seconds() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(start);
+    let interval = Duration::from_millis(1500);
+
+    assert!(scheduler.due(start, interval));
+    assert!(!scheduler.due(start + Duration::from_millis(1200), interval));
+    assert!(scheduler.due(start + Duration::from_millis(1500), interval));
+}
+
+#[test]
+fn scheduler_skips_ahead_after_a_long_stall() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(start);
+    let interval = Duration::from_millis(10);
+    let later = start + Duration::from_secs(10);
+
+    let mut ticks = 0;
+    while scheduler.due(later, interval) {
+        ticks += 1;
+    }
+
+    assert!(ticks <= 5);
+    assert_eq!(scheduler.time_to_next(later), interval);
+}
+
+#[test]
+fn resumed_scheduler_waits_a_full_interval() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(start);
+    let interval = Duration::from_millis(100);
+    let later = start + Duration::from_secs(3);
+
+    scheduler.resume(later, interval);
+    assert!(!scheduler.due(later, interval));
+    assert!(scheduler.due(later + interval, interval));
+}
+
+// -------------- determinism --------------
+
+#[test]
+fn same_seed_and_script_give_the_same_game() {
+    let script: Vec<Command> = [Command::Up, Command::None, Command::Right, Command::Down, Command::None]
+        .iter()
+        .cycle()
+        .take(200)
+        .copied()
+        .collect();
+
+    let mut a = Simulation::new(Difficulty::Easy, FIELD, SEED);
+    let mut b = Simulation::new(Difficulty::Easy, FIELD, SEED);
+    a.run(&script);
+    b.run(&script);
+
+    assert_eq!(a.events, b.events);
+    assert!(a.state.snake.segments().eq(b.state.snake.segments()));
+    assert!(a.state.items.iter().map(|item| (item.pos, item.kind.name))
+        .eq(b.state.items.iter().map(|item| (item.pos, item.kind.name))));
+    assert_eq!(a.state.score, b.state.score);
+}
+
+// -------------- snake glyphs --------------
+
+// The snake's glyphs from head to tail.
+fn glyphs(sim: &Simulation, theme: &str) -> String {
+    sim.state.snake.glyphs(&Theme::find(theme).unwrap()).iter().rev().map(|(_, glyph)| glyph).collect()
+}
+
+#[test]
+fn snake_has_a_head_body_and_tail() {
+    let sim = sim(Difficulty::Easy).with_snake(vec![(5,3),(5,4),(4,4),(3,4),(3,5),(3,6)], (0,-1));
+
+    assert_eq!(glyphs(&sim, "Classic"), "▲┛━┏┃•");
+    assert_eq!(glyphs(&sim, "ASCII"), "^+-+|o");
+}
+
+#[test]
+fn snake_glyphs_join_across_wraparound_seams() {
+    let across = sim(Difficulty::Easy).with_snake(vec![(1,4),(10,4),(9,4)], (1,0));
+    assert_eq!(glyphs(&across, "Classic"), "▶━•");
+
+    let corner = sim(Difficulty::Easy).with_snake(vec![(1,8),(10,8),(10,1),(10,2)], (1,0));
+    assert_eq!(glyphs(&corner, "Classic"), "▶┏┃•");
+}
+
+#[test]
+fn themes_without_snake_pieces_use_the_snake_glyph() {
+    let theme = Theme::parse("name: Plain\nsnake: ✿ green\nwall: #").unwrap();
+    let sim = sim(Difficulty::Easy).with_snake(vec![(5,3),(5,4),(4,4)], (0,-1));
+
+    assert!(sim.state.snake.glyphs(&theme).iter().all(|(_, glyph)| *glyph == '✿'));
+}
+
+// -------------- rendering --------------
+
+// Bytes sent per frame over a short game, drawing only the changes against
+// redrawing the whole screen each time. To see the numbers:
+//     cargo test -- --nocapture frame_bytes
+#[test]
+fn frame_bytes_drop_when_only_changes_are_sent() {
+    const TICKS: usize = 50;
+
+    let mut app = App::new(&GameOptions::new(Difficulty::Easy, Some((76, 20)), Rules::Wrap), None, SEED);
+    let mut screen = Screen::new((80, 25));
+    let (mut full, mut diff) = (0, 0);
+
+    for _ in 0..TICKS {
+        app.state.advance(Command::None);
+
+        app.render(&mut screen);
+        diff += screen.present(&mut std::io::sink());
+
+        screen.invalidate();
+        app.render(&mut screen);
+        full += screen.present(&mut std::io::sink());
+    }
+
+    println!("bytes per frame: {} full, {} changes only", full / TICKS, diff / TICKS);
+    assert!(diff * 10 < full);
+    assert_eq!(screen.average_bytes(), ((full + diff) / (2 * TICKS)) as u64);
+}
+
+// -------------- score file --------------
+
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("snake-{}-{}", process::id(), name))
+}
+
+#[test]
+fn unversioned_score_files_are_migrated() {
+    let old = r#"{"scores":[{"score":{"last":3,"best":9},"difficulty":"Hard","size":null}]}"#;
+    let board = ScoreBoard::parse(old).unwrap();
+
+    assert!(board.to_string().starts_with("Wrap Hard\n  full window  last:    3  best:    9\n"));
+    assert!(board.to_json().contains("\"version\": 4"));
+    assert_eq!(ScoreBoard::parse(&board.to_json()).unwrap().to_json(), board.to_json());
+}
+
+#[test]
+fn best_scores_become_nameless_records() {
+    let v2 = r#"{"version":2,"scores":[],"levels":{"Garden":{"last":4,"best":30}}}"#;
+    let board = ScoreBoard::parse(v2).unwrap();
+
+    assert_eq!(board.level_best("Garden"), 30);
+    assert!(board.to_string().contains("   1. -               30"));
+}
+
+#[test]
+fn fixed_window_slots_get_their_field_size() {
+    let v3 = r#"{"version":3,"levels":{},"scores":[
+        {"score":{"last":0,"top":[]},"difficulty":"Easy","size":null,"rules":"Wrap"},
+        {"score":{"last":5,"top":[]},"difficulty":"Easy","size":[80,25],"rules":"Walls"}
+    ]}"#;
+    let board = ScoreBoard::parse(v3).unwrap();
+
+    assert_eq!(board.to_string(), "Walls Easy\n  76x21        last:    5  best:    0\n");
+}
+
+#[test]
+fn unreadable_score_files_are_refused() {
+    assert!(ScoreBoard::parse("{\"scores\": [").is_err());
+    assert!(ScoreBoard::parse("[1, 2]").is_err());
+    assert_eq!(ScoreBoard::parse(r#"{"version": 99, "scores": [], "levels": {}}"#).err(), Some(ParseError::Newer(99)));
+    assert!(ScoreBoard::parse(r#"{"version": 0, "scores": [], "levels": {}}"#).is_err());
+}
+
+#[test]
+fn corrupt_score_file_is_backed_up_and_replaced() {
+    let path = temp_path("corrupt.json");
+    fs::write(&path, "not json").unwrap();
+
+    let (board, warning) = ScoreBoard::load_or_recover(&path).unwrap();
+    let backup = temp_path("corrupt.json.bak");
+
+    assert!(warning.is_some());
+    assert_eq!(board.to_json(), ScoreBoard::new().to_json());
+    assert!(!path.exists());
+    assert_eq!(fs::read_to_string(&backup).unwrap(), "not json");
+
+    fs::remove_file(backup).unwrap();
+}
+
+#[test]
+fn score_files_that_cannot_be_read_are_left_alone() {
+    let path = temp_path("unreadable.json");
+    fs::create_dir(&path).unwrap();
+
+    assert!(ScoreBoard::load_or_recover(&path).is_err());
+    assert!(path.is_dir());
+    assert!(!temp_path("unreadable.json.bak").exists());
+
+    fs::remove_dir(path).unwrap();
+}
+
+#[test]
+fn scores_are_saved_whole() {
+    let dir = temp_path("data");
+    let path = dir.join("nested").join("scores.json");
+    let board = ScoreBoard::new().update(ScoreKey::Level("Garden"), Record { score: 12, ..Record::default() });
+
+    board.save(&path).unwrap();
+
+    assert_eq!(ScoreBoard::load(&path).unwrap().level_best("Garden"), 12);
+    assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+// -------------- custom games --------------
+
+#[test]
+fn custom_games_start_with_their_speed_and_length() {
+    let options = GameOptions { speed: 20, length: 7, ..GameOptions::new(Difficulty::Hard, Some((60, 20)), Rules::Walls) };
+    let app = App::new(&options, None, SEED);
+
+    assert_eq!(app.state.speed, 20);
+    assert_eq!(app.state.snake.len(), 7);
+    assert_eq!(app.state.snake.head_pos(), (7, 1));
+    assert_eq!(app.state.frame.field(), (60, 20));
+}
+
+#[test]
+fn custom_games_must_fit_the_window() {
+    let options = GameOptions::new(Difficulty::Easy, Some((60, 18)), Rules::Wrap);
+    let window = (80, 24);
+
+    assert_eq!(options.check(window), Ok(()));
+    assert_eq!(GameOptions { size: Some((78, 19)), ..options.clone() }.check(window), Ok(()));
+    assert_eq!(window_for((78, 19)), window);
+    assert!(GameOptions { size: Some((20, 18)), ..options.clone() }.check(window).is_err());
+    assert!(GameOptions { size: Some((79, 18)), ..options.clone() }.check(window).is_err());
+    assert!(GameOptions { size: Some((60, 20)), ..options.clone() }.check(window).is_err());
+    assert!(GameOptions { speed: 0, ..options.clone() }.check(window).is_err());
+    assert!(GameOptions { length: 1, ..options.clone() }.check(window).is_err());
+    assert!(GameOptions { length: 31, ..options.clone() }.check(window).is_err());
+    assert_eq!(GameOptions { length: 30, ..options }.check(window), Ok(()));
+}
+
+// -------------- leaderboard --------------
+
+fn game(name: &str, score: u64) -> Record {
+    Record { name: name.to_string(), score, timestamp: 1_700_000_000, length: 9, duration: 75, seed: 7, ..Record::default() }
+}
+
+#[test]
+fn leaderboard_keeps_the_best_games_in_order() {
+    let key = ScoreKey::Mode(Mode { rules: Rules::Wrap, difficulty: Difficulty::Easy, size: Some((20, 10)) });
+    let mut board = ScoreBoard::new();
+
+    for score in 1..=LEADERBOARD_SIZE as u64 {
+        assert!(board.qualifies(key, score));
+        board = board.update(key, game("Ann", score));
+    }
+
+    assert!(!board.qualifies(key, 1));
+    assert!(board.qualifies(key, 2));
+
+    board = board.update(key, game("Bob", 5));
+    let (_, table) = &board.tables()[0];
+    let names: Vec<(&str, u64)> = table.top.iter().map(|record| (record.name.as_str(), record.score)).collect();
+
+    assert_eq!(table.top.len(), LEADERBOARD_SIZE);
+    assert_eq!(table.last, 5);
+    assert_eq!(names[0], ("Ann", 10));
+    assert_eq!(&names[5..7], &[("Ann", 5), ("Bob", 5)]);
+    assert_eq!(names[9], ("Ann", 2));
+}
+
+#[test]
+fn every_board_size_has_its_own_leaderboard() {
+    let mode = |rules, difficulty, size| ScoreKey::Mode(Mode { rules, difficulty, size: Some(size) });
+    let board = ScoreBoard::new()
+        .update(mode(Rules::Walls, Difficulty::Easy, (56, 16)), game("Ann", 8))
+        .update(mode(Rules::Wrap, Difficulty::Hard, (76, 21)), game("Bob", 3))
+        .update(mode(Rules::Wrap, Difficulty::Hard, (120, 40)), game("Cy", 6));
+
+    let titles: Vec<String> = board.tables().into_iter().map(|(title, _)| title).collect();
+    assert_eq!(titles, ["Wrap Hard 76x21", "Wrap Hard 120x40", "Walls Easy 56x16"]);
+
+    let text = board.to_string();
+    let headings: Vec<&str> = text.lines().filter(|line| !line.starts_with(' ')).collect();
+    assert_eq!(headings, ["Wrap Hard", "Walls Easy"]);
+}
+
+#[test]
+fn records_tell_how_the_game_went() {
+    let mut record = game("Ann", 42);
+    record.eaten.insert("Red apple".to_string(), 12);
+    record.eaten.insert("Gem".to_string(), 1);
+
+    assert_eq!(record.summary(), "Ann             42  len   9   1:15  2023-11-14");
+    assert_eq!(record.details(), "seed 7, ate 1 gem, 12 red");
+    assert!(!ScoreBoard::new().qualifies(ScoreKey::Level("Garden"), 0));
+}
+
+// -------------- benchmark --------------
+
+// Per-tick cost against snake length. Not part of the regular run:
+//     cargo test --release -- --ignored --nocapture tick_cost
+#[test]
+#[ignore]
+fn tick_cost_does_not_grow_with_snake_length() {
+    const BOARD: (u16, u16) = (400, 200);
+    const TICKS: usize = 300;
+
+    let mut costs = Vec::new();
+
+    for len in [10, 100, 1_000, 10_000, 50_000] {
+        // The body snakes back and forth from row 2 down, the head at (1,2)
+        // heading for the empty first row.
+        let body: Vec<(u16, u16)> = (0..len)
+            .map(|i| {
+                let row = (i / BOARD.0 as usize) as u16;
+                let col = (i % BOARD.0 as usize) as u16;
+                let x = if row.is_multiple_of(2) { col + 1 } else { BOARD.0 - col };
+                (x, row + 2)
+            })
+            .collect();
+
+        let script: Vec<Command> = std::iter::once(Command::None)
+            .chain(std::iter::once(Command::Right))
+            .chain(std::iter::repeat(Command::None))
+            .take(TICKS)
+            .collect();
+
+        let best = (0..5)
+            .map(|_| {
+                let mut sim = Simulation::new(Difficulty::Easy, BOARD, SEED)
+                    .with_items(&[("Red apple", (BOARD.0, BOARD.1))])
+                    .with_snake(body.clone(), (0,-1));
+
+                let start = Instant::now();
+                sim.run(&script);
+                let elapsed = start.elapsed();
+
+                assert!(!sim.state.game_over);
+                elapsed / TICKS as u32
+            })
+            .min()
+            .unwrap();
+
+        println!("length {:>6}: {:>8?} per tick", len, best);
+        costs.push(best);
+    }
+
+    let shortest = costs[0];
+    let longest = costs[costs.len() - 1];
+    assert!(longest < shortest * 10, "tick cost grew from {:?} to {:?}", shortest, longest);
+}
@@ -0,0 +1,126 @@
+// The help screen. Its pages are built from the key bindings, the rules and
+// `ITEM_KINDS`, so that they say what the game really does.
+
+use std::io::Write;
+use termion::{ AsyncReader, terminal_size, clear, event::Key };
+
+use crate::io::renderable::{ Frame, Renderable, CenteredPanel, boxed };
+use crate::io::input::MAX_QUEUED;
+use crate::io::layout::{ text_size, wait_keys_fitting };
+use crate::io::keymap::{ Keymap, ACTIONS, key_name };
+use crate::io::theme;
+use crate::game::items::ITEM_KINDS;
+use crate::game::state::{ Command, START_SPEED, ITEMS_ON_BOARD };
+use crate::menu::{ Difficulty, Rules };
+
+pub(crate) const PAGES: [&str; 3] = ["CONTROLS", "RULES", "ITEMS"];
+
+// What each action does beyond its name.
+fn action_note(cmd: Command) -> &'static str {
+    match cmd {
+        Command::Pause => "also when the window loses focus",
+        Command::Rebind => "change the key bindings",
+        Command::Help => "this screen",
+        Command::Quit => "asks before ending the game",
+        _ => ""
+    }
+}
+
+pub(crate) fn controls(keymap: &Keymap) -> Vec<String> {
+    let mut lines: Vec<String> = ACTIONS.iter()
+        .map(|(cmd, name)| {
+            let keys: Vec<String> = keymap.keys(*cmd).into_iter().map(key_name).collect();
+            let keys = if keys.is_empty() { "-".to_string() } else { keys.join(" ") };
+            format!("{:6} {:12} {}", name, keys, action_note(*cmd)).trim_end().to_string()
+        })
+        .collect();
+
+    lines.push(String::new());
+    lines.push(format!("Turns typed quickly are played one per tick, up to {}.", MAX_QUEUED));
+    lines
+}
+
+// The rules of the game being played, or of every mode when `mode` is
+// `None`.
+pub(crate) fn rules(mode: Option<(Difficulty, Rules)>) -> Vec<String> {
+    let difficulties = mode.map_or(vec![Difficulty::Easy, Difficulty::Hard], |(difficulty, _)| vec![difficulty]);
+    let borders = mode.map_or(vec![Rules::Wrap, Rules::Walls], |(_, rules)| vec![rules]);
+    let mut lines = Vec::new();
+
+    for difficulty in difficulties {
+        lines.push(match difficulty {
+            Difficulty::Easy => "Easy: turning straight back is ignored.",
+            Difficulty::Hard => "Hard: the snake can turn straight back, into itself."
+        }.to_string());
+    }
+
+    for rules in borders {
+        lines.push(match rules {
+            Rules::Wrap => "Wrap: the snake leaves on one side and comes back on the other.",
+            Rules::Walls => "Walls: running into the border ends the game."
+        }.to_string());
+    }
+
+    lines.push(String::new());
+    lines.push("Running into the snake or a wall ends the game.".to_string());
+    lines.push(format!("The game starts at speed {}; apples make it faster.", START_SPEED));
+    lines.push(format!("There are {} items on the board, now and then a bonus too.", ITEMS_ON_BOARD));
+    lines.push("Filling the whole board wins the game.".to_string());
+    lines
+}
+
+pub(crate) fn items() -> Vec<String> {
+    let mut lines: Vec<String> = ITEM_KINDS.iter()
+        .map(|kind| {
+            let mut effect = kind.effect.describe();
+            if kind.speed_up > 0 {
+                effect.push_str(&format!(", speed +{}", kind.speed_up));
+            }
+            format!("{} {:12} {:>2} pts  {}", theme::current().item(kind).glyph, kind.name, kind.points, effect)
+        })
+        .collect();
+
+    let bonus: Vec<String> = ITEM_KINDS.iter()
+        .filter_map(|kind| kind.lifetime.map(|ticks| format!("{} {} ticks", kind.name, ticks)))
+        .collect();
+
+    lines.push(String::new());
+    lines.push("Bonus items vanish after a while and are worth less".to_string());
+    lines.push(format!("as time runs out: {}.", bonus.join(", ")));
+    lines
+}
+
+// The help pages from `page` on, flipped with left and right; any other key
+// closes them. In a game `mode` is the game's difficulty and rules.
+pub(crate) fn show_help<W: Write>(stdin: &mut AsyncReader, stdout: &mut W, keymap: &Keymap, mode: Option<(Difficulty, Rules)>, mut page: usize) {
+    loop {
+        let mut lines = vec![
+            format!("HELP: {} ({}/{})", PAGES[page], page + 1, PAGES.len()),
+            String::new()
+        ];
+
+        lines.extend(match page {
+            0 => controls(keymap),
+            1 => rules(mode),
+            _ => items()
+        });
+
+        lines.push(String::new());
+        lines.push("left/right: page  any other key: back".to_string());
+
+        let content = boxed(&lines);
+        let keys = wait_keys_fitting(stdin, stdout, text_size(&content), |stdout| {
+            write!(stdout, "{}", clear::All).unwrap();
+            CenteredPanel { content: content.iter().map(String::as_str).collect(), frame: Frame::new((1, 1), terminal_size().unwrap()) }.render(stdout);
+            stdout.flush().unwrap();
+        });
+
+        for key in keys {
+            match key {
+                Key::Right => page = (page + 1) % PAGES.len(),
+                Key::Left => page = (page + PAGES.len() - 1) % PAGES.len(),
+                _ => return
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::{ collections::VecDeque, io::Read };
+use termion::event::{ self, Event, Key };
+use crate::{
+    io::keymap::Keymap,
+    game::state::{ Command, GameState }
+};
+
+// Turns typed ahead of the snake beyond this many are dropped, so that a
+// held key does not leave a backlog behind.
+pub(crate) const MAX_QUEUED: usize = 4;
+
+// xterm focus reporting: once enabled the terminal sends these whenever its
+// window gains or loses focus. termion does not know them, so they are
+// picked out before the rest of the input is handed to its parser.
+pub(crate) const ENABLE_FOCUS_EVENTS: &str = "\x1b[?1004h";
+pub(crate) const DISABLE_FOCUS_EVENTS: &str = "\x1b[?1004l";
+const FOCUS_IN: &[u8] = b"\x1b[I";
+const FOCUS_OUT: &[u8] = b"\x1b[O";
+
+// A key press, or a report from the terminal.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Input {
+    Key(Key),
+    FocusLost
+}
+
+// Collects every key pressed between two ticks, so that a quick sequence of
+// turns is played one per tick instead of losing all but one of them.
+pub(crate) struct InputQueue {
+    turns: VecDeque<Command>
+}
+
+impl InputQueue {
+    pub(crate) fn new() -> InputQueue {
+        InputQueue { turns: VecDeque::new() }
+    }
+
+    // Reads all pending keys. Turns are queued for the coming ticks; any
+    // other command is returned so that it can be acted on at once.
+    pub(crate) fn poll<R: Read>(&mut self, stdin: &mut R, keymap: &Keymap) -> Vec<Command> {
+        let mut immediate = Vec::new();
+
+        let commands = read_input(stdin).into_iter().map(|input| match input {
+            Input::Key(key) => keymap.command(key),
+            Input::FocusLost => Command::FocusLost
+        });
+
+        for cmd in commands {
+            match cmd {
+                Command::None => {},
+                cmd if cmd.dir().is_some() => {
+                    if self.turns.len() < MAX_QUEUED {
+                        self.turns.push_back(cmd);
+                    }
+                },
+                cmd => immediate.push(cmd)
+            }
+        }
+
+        immediate
+    }
+
+    // The turn to play on the next tick. Queued turns that would not change
+    // anything, such as repeating the current direction, are skipped rather
+    // than spending a tick on them.
+    pub(crate) fn next_turn(&mut self, state: &GameState) -> Command {
+        while let Some(cmd) = self.turns.pop_front() {
+            if state.turns(cmd) {
+                return cmd;
+            }
+        }
+
+        Command::None
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.turns.clear();
+    }
+}
+
+// Everything pending on `stdin`, in the order it was typed.
+pub(crate) fn read_input<R: Read>(stdin: &mut R) -> Vec<Input> {
+    let mut bytes = Vec::new();
+    stdin.read_to_end(&mut bytes).unwrap();
+
+    let mut inputs = Vec::new();
+    let mut rest = &bytes[..];
+
+    while let Some((&first, tail)) = rest.split_first() {
+        if rest.starts_with(FOCUS_OUT) {
+            inputs.push(Input::FocusLost);
+            rest = &rest[FOCUS_OUT.len()..];
+        } else if rest.starts_with(FOCUS_IN) {
+            rest = &rest[FOCUS_IN.len()..];
+        } else if first == b'\x1b' && tail.is_empty() {
+            // termion only reads a lone escape as the start of a sequence.
+            inputs.push(Input::Key(Key::Esc));
+            rest = tail;
+        } else {
+            let mut iter = tail.iter().map(|byte| Ok(*byte));
+            if let Ok(Event::Key(key)) = event::parse_event(first, &mut iter) {
+                inputs.push(Input::Key(key));
+            }
+            rest = &tail[tail.len() - iter.len()..];
+        }
+    }
+
+    inputs
+}
@@ -0,0 +1,230 @@
+// Key bindings are kept in `keys.txt` under `storage::data_dir()`, one `action: keys` line per action,
+// keys separated by spaces:
+//
+//     preset: vim
+//     pause: p space
+//
+// `preset` picks the bindings to start from; the lines after it replace
+// those of single actions. A key is a single character or one of `up`,
+// `down`, `left`, `right`, `space`, `enter`, `tab`, `esc` and `backspace`.
+
+use std::{ fs, io, path::{ Path, PathBuf } };
+use termion::event::Key;
+use crate::{ game::state::Command, io::storage };
+
+const KEYMAP_FILE: &str = "keys.txt";
+
+// Every action that can be bound, in the order the rebinding screen lists
+// them.
+pub(crate) const ACTIONS: [(Command, &str); 8] = [
+    (Command::Up, "up"),
+    (Command::Down, "down"),
+    (Command::Left, "left"),
+    (Command::Right, "right"),
+    (Command::Pause, "pause"),
+    (Command::Rebind, "keys"),
+    (Command::Help, "help"),
+    (Command::Quit, "quit")
+];
+
+// Actions a key bindings file may not leave without a key.
+const REQUIRED: [Command; 3] = [Command::Quit, Command::Pause, Command::Rebind];
+
+// Bindings for each action of `ACTIONS`, in the same order.
+const PRESETS: [(&str, [&str; 8]); 4] = [
+    ("wasd",   ["w up", "s down", "a left", "d right", "p", "r", "?", "q"]),
+    ("vim",    ["k up", "j down", "h left", "l right", "p", "r", "?", "q"]),
+    ("azerty", ["z up", "s down", "q left", "d right", "p", "r", "?", "x esc"]),
+    ("numpad", ["8 up", "2 down", "4 left", "6 right", "5", "r", "?", "q"])
+];
+
+const KEY_NAMES: [(Key, &str); 9] = [
+    (Key::Up, "up"),
+    (Key::Down, "down"),
+    (Key::Left, "left"),
+    (Key::Right, "right"),
+    (Key::Char(' '), "space"),
+    (Key::Char('\n'), "enter"),
+    (Key::Char('\t'), "tab"),
+    (Key::Esc, "esc"),
+    (Key::Backspace, "backspace")
+];
+
+pub(crate) fn key_name(key: Key) -> String {
+    match KEY_NAMES.iter().find(|(k, _)| *k == key) {
+        Some((_, name)) => name.to_string(),
+        None => match key {
+            Key::Char(c) => c.to_string(),
+            other => format!("{:?}", other).to_lowercase()
+        }
+    }
+}
+
+fn parse_key(name: &str) -> Result<Key, String> {
+    if let Some((key, _)) = KEY_NAMES.iter().find(|(_, n)| n.eq_ignore_ascii_case(name)) {
+        return Ok(*key);
+    }
+
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(Key::Char(c)),
+        _ => Err(format!("unknown key '{}'", name))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Keymap {
+    // The preset these bindings started from.
+    pub(crate) preset: &'static str,
+    bindings: Vec<(Key, Command)>,
+    // Where changes are saved.
+    pub(crate) file: PathBuf,
+    // Why the latest change could not be saved. Bindings are changed in raw
+    // mode, so this is reported once the terminal is back to normal.
+    pub(crate) save_error: Option<String>
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap::preset(PRESETS[0].0).unwrap()
+    }
+}
+
+impl Keymap {
+    pub(crate) fn preset(name: &str) -> Option<Keymap> {
+        let (preset, keys) = PRESETS.iter().find(|(preset, _)| preset.eq_ignore_ascii_case(name))?;
+        let mut keymap = Keymap { preset, bindings: Vec::new(), file: Keymap::default_path(), save_error: None };
+
+        for ((cmd, _), keys) in ACTIONS.iter().zip(keys) {
+            for key in keys.split_whitespace() {
+                keymap.bindings.push((parse_key(key).unwrap(), *cmd));
+            }
+        }
+
+        Some(keymap)
+    }
+
+    pub(crate) fn preset_names() -> impl Iterator<Item = &'static str> {
+        PRESETS.iter().map(|(name, _)| *name)
+    }
+
+    // Switches to the preset after the one these bindings started from,
+    // keeping the file they are saved to.
+    pub(crate) fn next_preset(&mut self) {
+        let names: Vec<&str> = Keymap::preset_names().collect();
+        let next = names.iter().position(|name| *name == self.preset).map_or(0, |i| (i + 1) % names.len());
+        *self = Keymap { file: self.file.clone(), save_error: self.save_error.take(), ..Keymap::preset(names[next]).unwrap() };
+    }
+
+    pub(crate) fn default_path() -> PathBuf {
+        storage::data_dir().join(KEYMAP_FILE)
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<Keymap, String> {
+        let mut keymap = Keymap::default();
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once(':').ok_or(format!("invalid line '{}'", line))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            if key == "preset" {
+                keymap = Keymap::preset(value).ok_or(format!("unknown preset '{}'", value))?;
+                continue;
+            }
+
+            let (cmd, _) = ACTIONS.iter().find(|(_, name)| *name == key).ok_or(format!("unknown action '{}'", key))?;
+            let keys = value.split_whitespace().map(parse_key).collect::<Result<Vec<_>, _>>()?;
+
+            keymap.bindings.retain(|(_, bound)| bound != cmd);
+            for key in keys {
+                keymap.bind(key, *cmd);
+            }
+        }
+
+        // Without these the game could only be left by losing it.
+        for (cmd, name) in ACTIONS.iter().filter(|(cmd, _)| REQUIRED.contains(cmd)) {
+            if keymap.keys(*cmd).is_empty() {
+                return Err(format!("no key left for '{}'", name));
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    // The file's bindings, or the default ones when there is no file or it
+    // does not parse. A `keys.txt` left in the current directory by older
+    // versions is moved to the data directory first.
+    pub(crate) fn load() -> Keymap {
+        let path = Keymap::default_path();
+        storage::adopt(Path::new(KEYMAP_FILE), &path);
+        Keymap::load_from(&path)
+    }
+
+    pub(crate) fn load_from(path: &Path) -> Keymap {
+        Keymap::read(path).unwrap_or_default()
+    }
+
+    // The bindings in `path`, which later changes are saved back to.
+    pub(crate) fn read(path: &Path) -> Result<Keymap, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("cannot read '{}': {}", path.display(), err))?;
+        let keymap = Keymap::parse(&text)?;
+
+        Ok(Keymap { file: path.to_path_buf(), ..keymap })
+    }
+
+    pub(crate) fn save(&self) -> io::Result<()> {
+        storage::write_atomic(&self.file, self.to_text().as_bytes())
+    }
+
+    // Saves the bindings, keeping the error for later when that fails.
+    pub(crate) fn try_save(&mut self) {
+        if let Err(err) = self.save() {
+            self.save_error = Some(err.to_string());
+        }
+    }
+
+    pub(crate) fn to_text(&self) -> String {
+        let mut text = format!("preset: {}\n", self.preset);
+
+        for (cmd, name) in ACTIONS {
+            let keys: Vec<String> = self.keys(cmd).into_iter().map(key_name).collect();
+            text.push_str(&format!("{}: {}\n", name, keys.join(" ")));
+        }
+
+        text
+    }
+
+    pub(crate) fn command(&self, key: Key) -> Command {
+        self.bindings.iter()
+            .find(|(bound, _)| *bound == key)
+            .map_or(Command::None, |(_, cmd)| *cmd)
+    }
+
+    pub(crate) fn keys(&self, cmd: Command) -> Vec<Key> {
+        self.bindings.iter().filter(|(_, bound)| *bound == cmd).map(|(key, _)| *key).collect()
+    }
+
+    // Adds `key` to the keys of `cmd`, taking it away from any other action.
+    pub(crate) fn bind(&mut self, key: Key, cmd: Command) {
+        self.bindings.retain(|(bound, _)| *bound != key);
+        self.bindings.push((key, cmd));
+    }
+
+    // Makes `key` the only key for `cmd`. An action that loses its only key
+    // to `cmd` gets the keys `cmd` had instead, so that no action is left
+    // without a key.
+    pub(crate) fn rebind(&mut self, cmd: Command, key: Key) {
+        let (old, other) = (self.keys(cmd), self.command(key));
+
+        self.bindings.retain(|(_, bound)| *bound != cmd);
+        self.bind(key, cmd);
+
+        if other != Command::None && self.keys(other).is_empty() {
+            self.bindings.extend(old.into_iter().map(|key| (key, other)));
+        }
+    }
+}
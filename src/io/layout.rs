@@ -0,0 +1,102 @@
+use std::{ io::{ Read, Write }, thread, time::Duration };
+use termion::{ clear, cursor, terminal_size, event::Key, AsyncReader };
+use crate::io::input::{ read_input, Input };
+
+// How often the window size is checked while waiting for a key.
+const SIZE_POLL: Duration = Duration::from_millis(50);
+
+// Columns `text` takes up. Variation selectors, such as the one that asks
+// for the text form of the red apple, take none.
+pub(crate) fn width(text: &str) -> usize {
+    text.chars().filter(|c| !('\u{fe00}'..='\u{fe0f}').contains(c)).count()
+}
+
+// Width and height of a block of text lines.
+pub(crate) fn text_size<S: AsRef<str>>(lines: &[S]) -> (u16, u16) {
+    let width = lines.iter().map(|line| width(line.as_ref())).max().unwrap_or(0);
+    (width as u16, lines.len() as u16)
+}
+
+pub(crate) fn fits(needed: (u16, u16)) -> bool {
+    let (w, h) = terminal_size().unwrap();
+    w >= needed.0 && h >= needed.1
+}
+
+// Asks for a bigger window, in as little room as there is. Lines that do
+// not fit are cut rather than wrapped. `quit` names the key that gets out:
+// `q` in the menus, the bound one in a game.
+pub(crate) fn render_too_small<W: Write>(stdout: &mut W, needed: (u16, u16), quit: &str) {
+    let (w, h) = terminal_size().unwrap();
+    let lines = [
+        "Window too small".to_string(),
+        format!("need {}x{}", needed.0, needed.1),
+        format!("have {}x{}", w, h),
+        format!("{}: quit", quit)
+    ];
+
+    write!(stdout, "{}", clear::All).unwrap();
+    for (row, line) in (1..=h).zip(&lines) {
+        let line: String = line.chars().take(w as usize).collect();
+        write!(stdout, "{}{}", cursor::Goto(1, row), line).unwrap();
+    }
+    stdout.flush().unwrap();
+}
+
+// Like `wait_char`, for screens that need `needed` cells: `draw` is called
+// whenever the window changes size and is large enough, and the too-small
+// screen is shown instead while it is not. Only `q` gets through then.
+pub(crate) fn wait_char_fitting<W: Write, F: FnMut(&mut W)>(stdin: &mut AsyncReader, stdout: &mut W, needed: (u16, u16), mut draw: F) -> u8 {
+    let mut size = None;
+
+    loop {
+        let current = terminal_size().unwrap();
+        if size != Some(current) {
+            size = Some(current);
+            if fits(needed) {
+                draw(stdout);
+            } else {
+                render_too_small(stdout, needed, "q");
+            }
+        }
+
+        let mut buf = [0; 1];
+        if stdin.read(&mut buf).unwrap() == 1 && (fits(needed) || buf[0] == b'q') {
+            return buf[0];
+        }
+
+        thread::sleep(SIZE_POLL);
+    }
+}
+
+// `wait_char_fitting` for screens that take keys rather than bytes, such as
+// the arrows. Returns every key typed since, so that none are lost. Only `q`
+// and esc get through while the window is too small.
+pub(crate) fn wait_keys_fitting<W: Write, F: FnMut(&mut W)>(stdin: &mut AsyncReader, stdout: &mut W, needed: (u16, u16), mut draw: F) -> Vec<Key> {
+    let mut size = None;
+
+    loop {
+        let current = terminal_size().unwrap();
+        if size != Some(current) {
+            size = Some(current);
+            if fits(needed) {
+                draw(stdout);
+            } else {
+                render_too_small(stdout, needed, "q");
+            }
+        }
+
+        let keys: Vec<Key> = read_input(stdin).into_iter()
+            .filter_map(|input| match input {
+                Input::Key(key) => Some(key),
+                Input::FocusLost => None
+            })
+            .filter(|key| fits(needed) || matches!(key, Key::Char('q') | Key::Esc))
+            .collect();
+
+        if !keys.is_empty() {
+            return keys;
+        }
+
+        thread::sleep(SIZE_POLL);
+    }
+}
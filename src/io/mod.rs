@@ -5,6 +5,16 @@ use std::thread;
 use std::time::Duration;
 
 pub(crate) mod renderable;
+pub(crate) mod help;
+pub(crate) mod input;
+pub(crate) mod keymap;
+pub(crate) mod layout;
+pub(crate) mod screen;
+pub(crate) mod storage;
+pub(crate) mod theme;
+
+#[cfg(test)]
+mod tests;
 
 pub(crate) fn wait_char(reader: &mut AsyncReader) -> u8 {
     loop {
@@ -1,10 +1,16 @@
 use std::io::Write;
 use termion::{
     cursor,
-    color,
+    terminal_size,
     AsyncReader,
 };
-use crate::io::wait_char;
+use termion::{ clear, event::Key };
+use crate::{
+    io::{ wait_char, theme, layout },
+    io::keymap::{ Keymap, ACTIONS, key_name },
+    game::scores::MAX_NAME_LEN,
+    game::state::Command
+};
 
 pub(crate) trait Renderable {
     fn render<W: Write>(&self, stdout: &mut W);
@@ -24,33 +30,37 @@ impl Frame {
     pub(crate) fn render<W:Write>(&self, stdout: &mut W) {
         let (x, y) = self.pos;
         let (w, h) = self.size;
+        let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = theme::current().border;
+
+        if w < 2 || h < 2 {
+            return;
+        }
 
-        write!(stdout, "{}╭{}╮", cursor::Goto(x, y), "─".repeat((w - 2) as usize)).unwrap();
+        let line = horizontal.to_string().repeat((w - 2) as usize);
+        write!(stdout, "{}{}{}{}", cursor::Goto(x, y), top_left, line, top_right).unwrap();
 
         for i in 1..(h-1) {
-            write!(stdout, "{}│{}│", cursor::Goto(x, y + i), cursor::Goto(x+w-1,y+i)).unwrap();
+            write!(stdout, "{}{}{}{}", cursor::Goto(x, y + i), vertical, cursor::Goto(x+w-1,y+i), vertical).unwrap();
         }
-        write!(stdout, "{}╰{}╯", cursor::Goto(x, y + h-1), "─".repeat((w - 2) as usize)).unwrap();
+        write!(stdout, "{}{}{}{}", cursor::Goto(x, y + h-1), bottom_left, line, bottom_right).unwrap();
     }
 
     pub(crate) fn goto(&self, x: u16, y: u16) -> cursor::Goto {
         let (_x, _y) = self.pos;
-        return cursor::Goto(_x + x, _y + y)
+        cursor::Goto(_x + x, _y + y)
+    }
+
+    pub(crate) fn in_field(&self, (x, y): (i16, i16)) -> bool {
+        let (w, h) = self.field();
+
+        x >= 1 && y >= 1 && x <= w as i16 && y <= h as i16
     }
 
     pub(crate) fn field(&self) -> (u16, u16) {
         let (x, y) = self.pos;
         let (w, h) = self.size;
-        
-        (w - x - 1, h - y - 1)
-    }
 
-    pub(crate) fn random_point(&self) -> (u16, u16) {
-        let (w, h) = self.field();
-        let x: u16 = rand::random::<u16>() % w + 1;
-        let y: u16 = rand::random::<u16>() % h + 1;
-
-        (x, y)
+        (w.saturating_sub(x + 1), h.saturating_sub(y + 1))
     }
 }
 
@@ -65,13 +75,50 @@ pub(crate) struct CenteredPanel<'a> {
 
 impl Renderable for CenteredPanel<'_> {
     fn render<W:Write>(&self, stdout: &mut W) {
-        let mut row = (self.frame.size.1 - self.content.len() as u16) / 2;
-        for line in &self.content {
-            let col = (self.frame.size.0 - line.chars().count() as u16) / 2;
-            write!(stdout, "{}{}", cursor::Goto(col, row), line).unwrap();
-            row += 1;
+        let top = self.frame.pos.1 + self.frame.size.1.saturating_sub(self.content.len() as u16) / 2;
+        for (row, line) in (top..).zip(&self.content) {
+            let col = self.frame.pos.0 + self.frame.size.0.saturating_sub(layout::width(line) as u16) / 2;
+            write!(stdout, "{}{}", cursor::Goto(col, row), theme::current().boxes(line)).unwrap();
+        }
+    }
+}
+
+// Draws a rounded box around `lines`, the first line centered as a title and
+// the others left aligned.
+pub(crate) fn boxed(lines: &[String]) -> Vec<String> {
+    let width = lines.iter().map(|line| layout::width(line)).max().unwrap_or(0) + 4;
+    let mut result = vec![format!("╭{}╮", "─".repeat(width))];
+
+    for (index, line) in lines.iter().enumerate() {
+        if index == 0 {
+            result.push(format!("│{:^width$}│", line));
+        } else {
+            result.push(format!("│  {}{}│", line, " ".repeat(width - 2 - layout::width(line))));
         }
     }
+
+    result.push(format!("╰{}╯", "─".repeat(width)));
+    result
+}
+
+// A column chart of `values`, oldest on the left, `height` rows tall and
+// drawn with `bar`. The axis is marked with the largest value.
+pub(crate) fn bar_chart(values: &[u64], height: usize, bar: char) -> Vec<String> {
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+    let label = max.to_string().len();
+    let mut rows = Vec::new();
+
+    for row in (1..=height).rev() {
+        // Rounded up, so that any score shows.
+        let columns: String = values.iter()
+            .map(|value| if (value * height as u64).div_ceil(max) >= row as u64 { bar } else { ' ' })
+            .collect();
+        let mark = if row == height { max.to_string() } else { String::new() };
+        rows.push(format!("{:>label$} │{}", mark, columns));
+    }
+
+    rows.push(format!("{:>label$} ╰{}", 0, "─".repeat(values.len())));
+    rows
 }
 
 pub(crate) const GAME_OVER_SCREEN:[&str;5] =  ["╭────────────────────────────────╮" ,
@@ -80,16 +127,11 @@ pub(crate) const GAME_OVER_SCREEN:[&str;5] =  ["╭─────────
                                                "│                                │" ,
                                                "╰────────────────────────────────╯"];
 
-pub(crate) const MAIN_MENU_SCREEN:[&str;10] =  ["╭─────────────────────────────╮" ,
-                                                "│            SNAKE            │" ,
-                                                "│                             │" ,
-                                                "│      1. EASY MODE           │" ,
-                                                "│      2. HARD MODE           │" ,
-                                                "│      3. EASY MODE 80x25     │" ,
-                                                "│      4. HARD MODE 80x25     │" ,
-                                                "│      q. QUIT                │" ,
-                                                "│                             │" ,
-                                                "╰─────────────────────────────╯"];
+pub(crate) const WIN_SCREEN:[&str;5] =       ["╭────────────────────────────────╮" ,
+                                               "│                                │" ,
+                                               "│    YOU WIN! THE BOARD IS FULL  │" ,
+                                               "│                                │" ,
+                                               "╰────────────────────────────────╯"];
 
 pub(crate) const CONFIRM_QUIT_SCREEN:[&str;6] =  ["╭─────────────────────────────╮" ,
                                                   "│                             │" ,
@@ -98,25 +140,74 @@ pub(crate) const CONFIRM_QUIT_SCREEN:[&str;6] =  ["╭────────
                                                   "│                             │" ,
                                                   "╰─────────────────────────────╯"];
 
+// The pause panel, naming the keys that resume, show the help and quit.
+pub(crate) fn pause_screen(keymap: &Keymap) -> Vec<String> {
+    let key = |cmd| keymap.keys(cmd).first().map_or("-".to_string(), |key| key_name(*key));
+    let hint = format!("{}: resume   {}: help   {}: quit", key(Command::Pause), key(Command::Help), key(Command::Quit));
+    let width = (hint.chars().count() + 4).max(29);
+
+    let mut result = vec![format!("╭{}╮", "─".repeat(width))];
+    for line in ["", "PAUSED", &hint, ""] {
+        result.push(format!("│{:^width$}│", line));
+    }
+    result.push(format!("╰{}╯", "─".repeat(width)));
+    result
+}
+
 #[derive(Clone)]
 pub(crate) struct InfoPanel {
     pub(crate) score: u64,
     pub(crate) speed: u64,
+    // Labels of the timed effects still running.
+    pub(crate) effects: Vec<String>,
+    // What the bonus item on the board is worth right now.
+    pub(crate) bonus: Option<u64>,
+    // Bytes sent for the last frame and on average, when asked for with
+    // `FRAME_STATS_VAR`.
+    pub(crate) frame_bytes: Option<(usize, u64)>,
     pub(crate) frame: Frame
 }
 
 impl Renderable for InfoPanel {
     fn render<W:Write>(&self, stdout: &mut W) {
+        let theme = theme::current();
+        let label = theme::palette().fg(theme.label);
+
         self.frame.render(stdout);
         write!(stdout, "{}{}Score{}: {} {}Speed{}: {}", 
                 self.frame.goto(2, 1), 
-                color::Fg(color::Yellow),
-                color::Fg(color::Reset),
+                label,
+                theme::RESET,
                 self.score,
-                color::Fg(color::Yellow),
-                color::Fg(color::Reset),
+                label,
+                theme::RESET,
                 self.speed
             ).unwrap();
+
+        if let Some(bonus) = self.bonus {
+            write!(stdout, " {}Bonus{}: {}",
+                label,
+                theme::RESET,
+                bonus
+            ).unwrap();
+        }
+
+        if !self.effects.is_empty() {
+            write!(stdout, " {}Effects{}: {}",
+                label,
+                theme::RESET,
+                self.effects.join(", ")
+            ).unwrap();
+        }
+
+        if let Some((last, average)) = self.frame_bytes {
+            write!(stdout, " {}Bytes{}: {} avg {}",
+                label,
+                theme::RESET,
+                last,
+                average
+            ).unwrap();
+        }
     }
 }
 
@@ -131,6 +222,115 @@ pub(crate) fn confirm_quit<W:Write>(stdin: &mut AsyncReader, stdout: &mut W, fra
 
     let choice:u8 = wait_char(stdin);
 
-    return choice == b'y';
+    choice == b'y'
+}
+
+// Asks for the name to put on the leaderboard, starting from `name`. Esc,
+// or an empty name, keeps `name`.
+pub(crate) fn ask_name<W:Write>(stdin: &mut AsyncReader, stdout: &mut W, name: &str) -> String {
+    ask_text(stdin, stdout, "NEW HIGH SCORE", "Your name:", name).unwrap_or_else(|| name.to_string())
+}
+
+// A one line text field of up to `MAX_NAME_LEN` characters, starting from
+// `text`. Enter takes what was typed; esc, an empty text, or quitting from
+// the too-small screen gives `None`.
+pub(crate) fn ask_text<W:Write>(stdin: &mut AsyncReader, stdout: &mut W, title: &str, label: &str, text: &str) -> Option<String> {
+    let mut typed = text.to_string();
+
+    loop {
+        let lines = vec![
+            title.to_string(),
+            String::new(),
+            label.to_string(),
+            format!("{:MAX_NAME_LEN$}", format!("{}_", typed)),
+            String::new(),
+            "enter: save  esc: skip".to_string()
+        ];
+
+        let content = boxed(&lines);
+        let needed = layout::text_size(&content);
+        let keys = layout::wait_keys_fitting(stdin, stdout, needed, |stdout| {
+            write!(stdout, "{}", clear::All).unwrap();
+            CenteredPanel { content: content.iter().map(String::as_str).collect(), frame: Frame::new((1, 1), terminal_size().unwrap()) }.render(stdout);
+            stdout.flush().unwrap();
+        });
+
+        // Only q and esc get through the too-small screen, and neither is
+        // meant as text there.
+        if !layout::fits(needed) {
+            return None;
+        }
+
+        for key in keys {
+            match key {
+                Key::Char('\n') if !typed.trim().is_empty() => return Some(typed.trim().to_string()),
+                Key::Char('\n') | Key::Esc => return None,
+                Key::Backspace => { typed.pop(); },
+                Key::Char(c) if !c.is_control() && typed.chars().count() < MAX_NAME_LEN => typed.push(c),
+                _ => {}
+            }
+        }
+    }
 }
 
+// Lists what each action is bound to and lets the player pick new keys.
+// Changes are saved as they are made.
+pub(crate) fn rebind_keys<W:Write>(stdin: &mut AsyncReader, stdout: &mut W, keymap: &mut Keymap) {
+    let mut waiting = None;
+
+    loop {
+        let mut lines = vec![
+            "KEYS".to_string(),
+            String::new()
+        ];
+
+        for (index, (cmd, name)) in ACTIONS.iter().enumerate() {
+            let keys: Vec<String> = keymap.keys(*cmd).into_iter().map(key_name).collect();
+            let keys = match (waiting == Some(index), keys.is_empty()) {
+                (true, _) => "press a key, esc to cancel".to_string(),
+                (false, true) => "-".to_string(),
+                (false, false) => keys.join(" ")
+            };
+            lines.push(format!("{}. {:6} {}", index + 1, name, keys));
+        }
+
+        lines.push(String::new());
+        lines.push(format!("p. preset: {}", keymap.preset));
+        lines.push("q. back".to_string());
+
+        let content = boxed(&lines);
+        let needed = layout::text_size(&content);
+        let keys = layout::wait_keys_fitting(stdin, stdout, needed, |stdout| {
+            write!(stdout, "{}", clear::All).unwrap();
+            CenteredPanel { content: content.iter().map(String::as_str).collect(), frame: Frame::new((1, 1), terminal_size().unwrap()) }.render(stdout);
+            stdout.flush().unwrap();
+        });
+
+        // The q or esc that leaves the too-small screen is not a key to bind.
+        if !layout::fits(needed) {
+            return;
+        }
+
+        for key in keys {
+            if let Some(index) = waiting.take() {
+                if key != Key::Esc {
+                    keymap.rebind(ACTIONS[index].0, key);
+                    keymap.try_save();
+                }
+                continue;
+            }
+
+            match key {
+                Key::Char(c @ '1'..='9') if (c as usize - '1' as usize) < ACTIONS.len() => {
+                    waiting = Some(c as usize - '1' as usize);
+                },
+                Key::Char('p') => {
+                    keymap.next_preset();
+                    keymap.try_save();
+                },
+                Key::Char('q') | Key::Esc => return,
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::io::{ self, Write };
+use termion::{ clear, cursor };
+
+// A terminal colour as found in SGR sequences: the terminal's own default,
+// one of the 256 palette entries, or a 24 bit colour.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Color {
+    Default,
+    Ansi(u8),
+    Rgb(u8, u8, u8)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct Cell {
+    pub(crate) ch: char,
+    // A zero width character drawn along with `ch`, such as the variation
+    // selector that asks for the text form of ❤.
+    pub(crate) mark: Option<char>,
+    pub(crate) fg: Color,
+    pub(crate) bg: Color
+}
+
+const BLANK: Cell = Cell { ch: ' ', mark: None, fg: Color::Default, bg: Color::Default };
+
+enum Parse {
+    Text,
+    Escape,
+    Csi(String)
+}
+
+// A back buffer of styled cells. Renderables write their usual escape
+// sequences into it as if it were the terminal; `present` then sends only
+// the cells that differ from what the terminal already shows.
+pub(crate) struct Screen {
+    size: (u16, u16),
+    back: Vec<Cell>,
+    // What the terminal shows, or `None` when that is unknown and the next
+    // frame has to be drawn in full.
+    front: Option<Vec<Cell>>,
+    cursor: (u16, u16),
+    fg: Color,
+    bg: Color,
+    parse: Parse,
+    utf8: Vec<u8>,
+    // Frames presented and the bytes they took, for `average_bytes`.
+    frames: u64,
+    sent: u64
+}
+
+impl Screen {
+    pub(crate) fn new(size: (u16, u16)) -> Screen {
+        Screen {
+            size,
+            back: vec![BLANK; size.0 as usize * size.1 as usize],
+            front: None,
+            cursor: (1, 1),
+            fg: Color::Default,
+            bg: Color::Default,
+            parse: Parse::Text,
+            utf8: Vec::new(),
+            frames: 0,
+            sent: 0
+        }
+    }
+
+    // Bytes sent per frame so far, rounded down.
+    pub(crate) fn average_bytes(&self) -> u64 {
+        self.sent.checked_div(self.frames).unwrap_or(0)
+    }
+
+    pub(crate) fn resize(&mut self, size: (u16, u16)) {
+        if size != self.size {
+            *self = Screen::new(size);
+        }
+    }
+
+    // Forgets what the terminal shows, e.g. after something else drew over
+    // it, so that the next frame is sent in full.
+    pub(crate) fn invalidate(&mut self) {
+        self.front = None;
+    }
+
+    #[cfg(test)]
+    pub(crate) fn cell(&self, (x, y): (u16, u16)) -> Option<Cell> {
+        self.index((x, y)).map(|i| self.back[i])
+    }
+
+    fn index(&self, (x, y): (u16, u16)) -> Option<usize> {
+        let (w, h) = self.size;
+
+        if x < 1 || y < 1 || x > w || y > h {
+            return None;
+        }
+
+        Some((y - 1) as usize * w as usize + (x - 1) as usize)
+    }
+
+    fn put(&mut self, ch: char) {
+        if is_mark(ch) {
+            let (x, y) = self.cursor;
+            if let Some(i) = self.index((x.saturating_sub(1), y)) {
+                self.back[i].mark = Some(ch);
+            }
+            return;
+        }
+
+        if let Some(i) = self.index(self.cursor) {
+            self.back[i] = Cell { ch, mark: None, fg: self.fg, bg: self.bg };
+        }
+        self.cursor.0 = self.cursor.0.saturating_add(1);
+    }
+
+    fn feed(&mut self, byte: u8) {
+        match &mut self.parse {
+            Parse::Text => match byte {
+                b'\x1b' => self.parse = Parse::Escape,
+                b'\n' => self.cursor.1 = self.cursor.1.saturating_add(1),
+                b'\r' => self.cursor.0 = 1,
+                _ => {
+                    self.utf8.push(byte);
+                    if let Ok(text) = std::str::from_utf8(&self.utf8) {
+                        let ch = text.chars().next().unwrap();
+                        self.utf8.clear();
+                        self.put(ch);
+                    } else if self.utf8.len() >= 4 {
+                        self.utf8.clear();
+                    }
+                }
+            },
+            Parse::Escape => {
+                self.parse = if byte == b'[' { Parse::Csi(String::new()) } else { Parse::Text };
+            },
+            Parse::Csi(params) => match byte {
+                0x30..=0x3f => params.push(byte as char),
+                _ => {
+                    let params = std::mem::take(params);
+                    self.parse = Parse::Text;
+                    self.control(byte, &params);
+                }
+            }
+        }
+    }
+
+    // Acts on the few control sequences the renderables use; the rest, like
+    // hiding the cursor, do not change what a cell shows.
+    fn control(&mut self, command: u8, params: &str) {
+        let mut numbers = params.split(';').map(|n| n.parse::<u16>().unwrap_or(0));
+
+        match command {
+            b'H' => {
+                let y = numbers.next().unwrap_or(1).max(1);
+                let x = numbers.next().unwrap_or(1).max(1);
+                self.cursor = (x, y);
+            },
+            b'J' if params == "2" => self.back.fill(BLANK),
+            b'm' => self.style(&params.split(';').map(|n| n.parse::<u8>().unwrap_or(0)).collect::<Vec<_>>()),
+            _ => {}
+        }
+    }
+
+    fn style(&mut self, params: &[u8]) {
+        let mut params = params.iter().copied();
+
+        while let Some(param) = params.next() {
+            match param {
+                0 => { self.fg = Color::Default; self.bg = Color::Default; },
+                30..=37 => self.fg = Color::Ansi(param - 30),
+                90..=97 => self.fg = Color::Ansi(param - 90 + 8),
+                40..=47 => self.bg = Color::Ansi(param - 40),
+                100..=107 => self.bg = Color::Ansi(param - 100 + 8),
+                39 => self.fg = Color::Default,
+                49 => self.bg = Color::Default,
+                38 | 48 => {
+                    let color = match params.next() {
+                        Some(5) => Color::Ansi(params.next().unwrap_or(0)),
+                        Some(2) => Color::Rgb(params.next().unwrap_or(0), params.next().unwrap_or(0), params.next().unwrap_or(0)),
+                        _ => Color::Default
+                    };
+                    if param == 38 { self.fg = color } else { self.bg = color }
+                },
+                _ => {}
+            }
+        }
+    }
+
+    // Sends the cells that changed since the last frame and returns the
+    // number of bytes written. Runs of changed cells are written in one go,
+    // the cursor jumps over unchanged ones, and colours are only set when
+    // they differ from the previous cell written.
+    pub(crate) fn present<W: Write>(&mut self, out: &mut W) -> usize {
+        let mut buf = Vec::new();
+        let front = match self.front.take() {
+            Some(front) => front,
+            None => {
+                write!(buf, "{}", clear::All).unwrap();
+                vec![BLANK; self.back.len()]
+            }
+        };
+
+        let (w, h) = self.size;
+        let mut at: Option<(u16, u16)> = None;
+        let mut style = (Color::Default, Color::Default);
+
+        for y in 1..=h {
+            for x in 1..=w {
+                let i = self.index((x, y)).unwrap();
+                let cell = self.back[i];
+                if cell == front[i] {
+                    continue;
+                }
+
+                match at {
+                    Some((ax, ay)) if ay == y && ax == x => {},
+                    Some((ax, ay)) if ay == y && ax < x => write!(buf, "{}", cursor::Right(x - ax)).unwrap(),
+                    _ => write!(buf, "{}", cursor::Goto(x, y)).unwrap()
+                }
+
+                if (cell.fg, cell.bg) != style {
+                    write_style(&mut buf, style, (cell.fg, cell.bg));
+                    style = (cell.fg, cell.bg);
+                }
+
+                write!(buf, "{}", cell.ch).unwrap();
+                if let Some(mark) = cell.mark {
+                    write!(buf, "{}", mark).unwrap();
+                }
+                at = Some((x + 1, y));
+            }
+        }
+
+        if style != (Color::Default, Color::Default) {
+            write_style(&mut buf, style, (Color::Default, Color::Default));
+        }
+
+        out.write_all(&buf).unwrap();
+        out.flush().unwrap();
+        self.front = Some(self.back.clone());
+        self.frames += 1;
+        self.sent += buf.len() as u64;
+
+        buf.len()
+    }
+}
+
+impl Write for Screen {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        for byte in bytes {
+            self.feed(*byte);
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn is_mark(ch: char) -> bool {
+    matches!(ch, '\u{fe00}'..='\u{fe0f}' | '\u{200d}' | '\u{0300}'..='\u{036f}')
+}
+
+// One SGR sequence switching from the `from` colours to the `to` ones.
+fn write_style(buf: &mut Vec<u8>, from: (Color, Color), to: (Color, Color)) {
+    let mut params = Vec::new();
+
+    if from.0 != to.0 {
+        params.push(color_params(to.0, 38));
+    }
+    if from.1 != to.1 {
+        params.push(color_params(to.1, 48));
+    }
+
+    write!(buf, "\x1b[{}m", params.join(";")).unwrap();
+}
+
+fn color_params(color: Color, base: u8) -> String {
+    match color {
+        Color::Default => (base + 1).to_string(),
+        Color::Ansi(n) => format!("{};5;{}", base, n),
+        Color::Rgb(r, g, b) => format!("{};2;{};{};{}", base, r, g, b)
+    }
+}
@@ -0,0 +1,154 @@
+// Where the game keeps what it saves between runs, and how it writes it.
+
+use std::{ env, fmt, fs, io, path::{ Path, PathBuf }, process };
+
+const APP_DIR: &str = "snake";
+
+// `$XDG_DATA_HOME/snake`, or `~/.local/share/snake` when that is not set.
+// The current directory is the last resort, when there is no home either.
+pub(crate) fn data_dir() -> PathBuf {
+    let base = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").filter(|dir| !dir.is_empty()).map(|home| Path::new(&home).join(".local/share")));
+
+    match base {
+        Some(base) => base.join(APP_DIR),
+        None => PathBuf::from(".")
+    }
+}
+
+// `path` with `suffix` added to its file name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+// Writes `contents` to a temporary file next to `path` and renames it over
+// `path`, so that a crash or a full disk leaves either the old file or the
+// new one, never half of it.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+
+    // Named after the process, so that two games saving at once do not
+    // write to the same temporary file.
+    let temp = with_suffix(path, &format!(".{}.tmp", process::id()));
+    let result = fs::write(&temp, contents)
+        .and_then(|_| fs::File::open(&temp)?.sync_all())
+        .and_then(|_| fs::rename(&temp, path));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+// Moves `path` aside to `<path>.bak`, replacing an older backup, and returns
+// where it went.
+pub(crate) fn back_up(path: &Path) -> io::Result<PathBuf> {
+    let backup = with_suffix(path, ".bak");
+    fs::rename(path, &backup)?;
+    Ok(backup)
+}
+
+// Why the contents of a saved file cannot be used.
+#[derive(PartialEq, Debug)]
+pub(crate) enum ParseError {
+    // Damaged, or not a file of the game at all.
+    Corrupt(String),
+    // Written in a format newer than this build knows.
+    Newer(u64)
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Corrupt(err) => write!(f, "{}", err),
+            ParseError::Newer(version) => write!(f, "written by a newer version of the game (format {})", version)
+        }
+    }
+}
+
+impl From<String> for ParseError {
+    fn from(err: String) -> ParseError {
+        ParseError::Corrupt(err)
+    }
+}
+
+impl From<&str> for ParseError {
+    fn from(err: &str) -> ParseError {
+        ParseError::Corrupt(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ParseError {
+    fn from(err: serde_json::Error) -> ParseError {
+        ParseError::Corrupt(err.to_string())
+    }
+}
+
+// What `parse` makes of `path`, or `None` when there is no such file.
+// Corrupt contents are moved aside with `back_up`, and `None` is returned
+// with a warning saying so. Errors reading the file, and files written by a
+// newer version of the game, are returned as errors and the file is left
+// alone, as it may well be fine.
+pub(crate) fn load_or_back_up<T>(path: &Path, parse: fn(&str) -> Result<T, ParseError>) -> io::Result<(Option<T>, Option<String>)> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok((None, None)),
+        Err(err) => return Err(err)
+    };
+
+    let err = match String::from_utf8(bytes) {
+        Ok(text) => match parse(&text) {
+            Ok(value) => return Ok((Some(value), None)),
+            Err(err @ ParseError::Newer(_)) => return Err(io::Error::other(err.to_string())),
+            Err(ParseError::Corrupt(err)) => err
+        },
+        Err(err) => err.to_string()
+    };
+
+    let warning = match back_up(path) {
+        Ok(backup) => format!("{}: {}; moved to {}", path.display(), err, backup.display()),
+        Err(_) => format!("{}: {}", path.display(), err)
+    };
+    Ok((None, Some(warning)))
+}
+
+// Moves a file the game used to keep in the current directory to `path`,
+// unless there already is one there.
+pub(crate) fn adopt(old: &Path, path: &Path) {
+    if path.exists() || !old.is_file() {
+        return;
+    }
+
+    if let Ok(contents) = fs::read(old) {
+        if write_atomic(path, &contents).is_ok() {
+            let _ = fs::remove_file(old);
+        }
+    }
+}
+
+// Every `*.txt` file in `dir` that `parse` accepts, in file name order.
+// Broken files are skipped so that a typo in one of them does not take the
+// rest down.
+pub(crate) fn load_dir<T>(dir: &Path, parse: fn(&str) -> Result<T, String>) -> Vec<T> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    paths.sort();
+
+    paths.iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .filter_map(|text| parse(&text).ok())
+        .collect()
+}
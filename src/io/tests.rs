@@ -0,0 +1,378 @@
+use std::io::Write;
+use termion::{ clear, color, cursor, event::Key };
+use crate::{
+    menu::{ Difficulty, Rules, widget::{ Menu, MenuItem, Choice } },
+    io::input::InputQueue,
+    io::layout::{ self, text_size },
+    io::help,
+    io::renderable::{ Frame, CenteredPanel, Renderable, CONFIRM_QUIT_SCREEN, boxed, bar_chart },
+    io::keymap::Keymap,
+    io::screen::{ Screen, Color },
+    io::theme::{ Theme, Palette },
+    game::items::ITEM_KINDS,
+    game::sim::Simulation,
+    game::state::Command
+};
+
+fn sim(difficulty: Difficulty) -> Simulation {
+    Simulation::new(difficulty, (10, 8), 42).with_items(&[])
+}
+
+#[test]
+fn arrow_keys_and_letters_are_parsed() {
+    let mut input = InputQueue::new();
+    let sim = sim(Difficulty::Hard).with_snake(vec![(5,5),(5,6),(5,7)], (0,-1));
+
+    input.poll(&mut &b"\x1b[C"[..], &Keymap::default());
+    assert_eq!(input.next_turn(&sim.state), Command::Right);
+
+    input.poll(&mut &b"a"[..], &Keymap::default());
+    assert_eq!(input.next_turn(&sim.state), Command::Left);
+
+    input.poll(&mut &b"\x1b[B"[..], &Keymap::default());
+    assert_eq!(input.next_turn(&sim.state), Command::Down);
+}
+
+#[test]
+fn quit_is_reported_at_once() {
+    let mut input = InputQueue::new();
+    let immediate = input.poll(&mut &b"dq"[..], &Keymap::default());
+
+    assert_eq!(immediate, vec![Command::Quit]);
+}
+
+#[test]
+fn two_quick_turns_are_played_on_consecutive_ticks() {
+    let mut input = InputQueue::new();
+    let mut sim = sim(Difficulty::Easy);
+
+    // Moving right: up then left on screen, typed within one tick.
+    input.poll(&mut &b"\x1b[A\x1b[D"[..], &Keymap::default());
+
+    let cmd = input.next_turn(&sim.state);
+    sim.step(cmd);
+    assert_eq!(sim.state.snake.dir, (0,-1));
+
+    let cmd = input.next_turn(&sim.state);
+    sim.step(cmd);
+    assert_eq!(sim.state.snake.dir, (-1,0));
+}
+
+#[test]
+fn turns_that_change_nothing_do_not_use_up_a_tick() {
+    let mut input = InputQueue::new();
+    let sim = sim(Difficulty::Easy);
+
+    // Right is the current direction and left a reversal Easy mode ignores.
+    input.poll(&mut &b"dadw"[..], &Keymap::default());
+
+    assert_eq!(input.next_turn(&sim.state), Command::Up);
+    assert_eq!(input.next_turn(&sim.state), Command::None);
+}
+
+#[test]
+fn pause_is_reported_at_once() {
+    let mut input = InputQueue::new();
+    let immediate = input.poll(&mut &b"p"[..], &Keymap::default());
+
+    assert_eq!(immediate, vec![Command::Pause]);
+}
+
+#[test]
+fn focus_events_are_picked_out_of_the_input() {
+    let mut input = InputQueue::new();
+    let sim = sim(Difficulty::Easy);
+
+    let immediate = input.poll(&mut &b"\x1b[Iw\x1b[O\x1b[B"[..], &Keymap::default());
+
+    assert_eq!(immediate, vec![Command::FocusLost]);
+    assert_eq!(input.next_turn(&sim.state), Command::Up);
+}
+
+#[test]
+fn layouts_larger_than_their_frame_do_not_underflow() {
+    let tiny = Frame::new((1,1), (1,1));
+    assert_eq!(tiny.field(), (0,0));
+
+    let mut out = Vec::new();
+    tiny.render(&mut out);
+    CenteredPanel { content: CONFIRM_QUIT_SCREEN.to_vec(), frame: tiny }.render(&mut out);
+
+    assert_eq!(text_size(&CONFIRM_QUIT_SCREEN), (31, 6));
+}
+
+// -------------- screen --------------
+
+#[test]
+fn screen_keeps_what_renderables_write() {
+    let mut screen = Screen::new((10, 3));
+    write!(screen, "{}{}❤︎{}x", cursor::Goto(4, 2), color::Fg(color::Red), color::Fg(color::Reset)).unwrap();
+
+    let heart = screen.cell((4, 2)).unwrap();
+    assert_eq!((heart.ch, heart.mark, heart.fg), ('❤', Some('\u{fe0e}'), Color::Ansi(1)));
+
+    let x = screen.cell((5, 2)).unwrap();
+    assert_eq!((x.ch, x.fg), ('x', Color::Default));
+
+    write!(screen, "{}", clear::All).unwrap();
+    assert_eq!(screen.cell((4, 2)).unwrap().ch, ' ');
+}
+
+#[test]
+fn screen_sends_only_what_changed() {
+    let mut screen = Screen::new((20, 5));
+    let mut out = Vec::new();
+
+    write!(screen, "{}hello", cursor::Goto(1, 1)).unwrap();
+    let first = screen.present(&mut out);
+
+    write!(screen, "{}{}hello", clear::All, cursor::Goto(1, 1)).unwrap();
+    assert_eq!(screen.present(&mut out), 0);
+
+    write!(screen, "{}hallo", cursor::Goto(1, 1)).unwrap();
+    out.clear();
+    screen.present(&mut out);
+    assert_eq!(out, format!("{}a", cursor::Goto(2, 1)).into_bytes());
+
+    screen.invalidate();
+    assert_eq!(screen.present(&mut out), first);
+}
+
+#[test]
+fn screen_sets_colours_only_when_they_change() {
+    let mut screen = Screen::new((20, 1));
+    let mut out = Vec::new();
+
+    write!(screen, "{}{}ab{}c", cursor::Goto(1, 1), color::Fg(color::Green), color::Fg(color::Reset)).unwrap();
+    screen.invalidate();
+    screen.present(&mut out);
+
+    let expected = format!("{}{}\x1b[38;5;2mab\x1b[39mc", clear::All, cursor::Goto(1, 1));
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+}
+
+// -------------- themes --------------
+
+#[test]
+fn builtin_themes_parse() {
+    let names: Vec<String> = Theme::builtin().into_iter().map(|theme| theme.name).collect();
+    assert_eq!(names, vec!["Classic", "ASCII", "Neon"]);
+}
+
+#[test]
+fn ascii_theme_draws_everything_in_ascii() {
+    let theme = Theme::find("ascii").unwrap();
+
+    assert!(theme.snake.glyph.is_ascii() && theme.wall.glyph.is_ascii());
+    assert!(theme.border.iter().all(char::is_ascii));
+    assert!(ITEM_KINDS.iter().all(|kind| theme.item(kind).glyph.is_ascii()));
+    assert!(CONFIRM_QUIT_SCREEN.iter().all(|line| theme.boxes(line).is_ascii()));
+}
+
+#[test]
+fn theme_rejects_bad_files() {
+    assert!(Theme::parse("name: x\nsnake: o\n").is_err());
+    assert!(Theme::parse("name: x\nsnake: o pink\nwall: #").is_err());
+    assert!(Theme::parse("name: x\nsnake: o\nwall: #\nborder: ++").is_err());
+    assert!(Theme::parse("name: x\nsnake: o #12345g\nwall: #").is_err());
+    assert!(Theme::parse("name: x\nsnake: o #123456\nwall: # 200\nitem Gem: * light_cyan").is_ok());
+}
+
+#[test]
+fn colours_fall_back_to_what_the_terminal_has() {
+    let orange = Color::Rgb(255, 135, 0);
+
+    assert_eq!(Palette::TrueColor.fg(orange), "\x1b[38;2;255;135;0m");
+    assert_eq!(Palette::Ansi256.fg(orange), "\x1b[38;5;208m");
+    assert_eq!(Palette::Ansi16.fg(orange), "\x1b[33m");
+    assert_eq!(Palette::Ansi16.fg(Color::Ansi(196)), "\x1b[91m");
+    assert_eq!(Palette::Ansi16.fg(Color::Ansi(2)), "\x1b[32m");
+}
+
+// -------------- keymap --------------
+
+#[test]
+fn presets_bind_their_keys() {
+    let vim = Keymap::preset("vim").unwrap();
+    assert_eq!(vim.command(Key::Char('k')), Command::Up);
+    assert_eq!(vim.command(Key::Char('h')), Command::Left);
+    assert_eq!(vim.command(Key::Up), Command::Up);
+
+    let azerty = Keymap::preset("azerty").unwrap();
+    assert_eq!(azerty.command(Key::Char('z')), Command::Up);
+    assert_eq!(azerty.command(Key::Char('q')), Command::Left);
+    assert_eq!(azerty.command(Key::Esc), Command::Quit);
+
+    let numpad = Keymap::preset("numpad").unwrap();
+    assert_eq!(numpad.command(Key::Char('8')), Command::Up);
+    assert_eq!(numpad.command(Key::Char('2')), Command::Down);
+}
+
+#[test]
+fn keymap_file_overrides_a_preset() {
+    let keymap = Keymap::parse("preset: vim\npause: space p\n").unwrap();
+
+    assert_eq!(keymap.command(Key::Char('j')), Command::Down);
+    assert_eq!(keymap.command(Key::Char(' ')), Command::Pause);
+    assert_eq!(keymap.keys(Command::Pause), vec![Key::Char(' '), Key::Char('p')]);
+
+    assert!(Keymap::parse("jump: j").is_err());
+    assert!(Keymap::parse("up: F13").is_err());
+    assert!(Keymap::parse("preset: dvorak").is_err());
+}
+
+#[test]
+fn rebinding_moves_a_key_from_one_action_to_another() {
+    let mut keymap = Keymap::default();
+    keymap.rebind(Command::Left, Key::Char('w'));
+
+    assert_eq!(keymap.keys(Command::Left), vec![Key::Char('w')]);
+    assert_eq!(keymap.keys(Command::Up), vec![Key::Up]);
+
+    let text = keymap.to_text();
+    assert_eq!(Keymap::parse(&text).unwrap().to_text(), text);
+}
+
+#[test]
+fn rebinding_never_leaves_an_action_without_a_key() {
+    let mut keymap = Keymap::default();
+    keymap.rebind(Command::Left, Key::Char('q'));
+
+    assert_eq!(keymap.keys(Command::Left), vec![Key::Char('q')]);
+    assert_eq!(keymap.keys(Command::Quit), vec![Key::Char('a'), Key::Left]);
+
+    keymap.rebind(Command::Pause, Key::Char('r'));
+    assert_eq!(keymap.keys(Command::Rebind), vec![Key::Char('p')]);
+
+    assert!(Keymap::parse("quit:").is_err());
+    assert!(Keymap::parse("pause: q").is_err());
+    assert!(Keymap::parse("pause: q\nquit: esc").is_ok());
+}
+
+#[test]
+fn lone_escape_is_a_key() {
+    let mut input = InputQueue::new();
+    let immediate = input.poll(&mut &b"\x1b"[..], &Keymap::preset("azerty").unwrap());
+
+    assert_eq!(immediate, vec![Command::Quit]);
+}
+
+// -------------- menu widget --------------
+
+fn test_menu() -> Menu<u8> {
+    Menu::new("TEST", vec![
+        MenuItem::new(Some('1'), "ONE", 1),
+        MenuItem::new(Some('x'), "A LONGER ENTRY", 2),
+        MenuItem::back()
+    ])
+}
+
+#[test]
+fn menu_moves_with_arrows() {
+    let mut menu = test_menu();
+
+    assert_eq!(menu.handle(Key::Up), Choice::Stay);
+    assert_eq!(menu.selected, 2);
+    assert_eq!(menu.handle(Key::Down), Choice::Stay);
+    assert_eq!(menu.selected, 0);
+    assert_eq!(menu.handle(Key::Down), Choice::Stay);
+    assert_eq!(menu.handle(Key::Char('\n')), Choice::Pick(2));
+}
+
+#[test]
+fn menu_keys_are_shortcuts_not_turns() {
+    let mut menu = test_menu();
+
+    assert_eq!(menu.handle(Key::Char('x')), Choice::Pick(2));
+    assert_eq!(menu.selected, 1);
+
+    // Keys that steer the snake in some keymap do nothing else here.
+    for key in ['s', 'k', 'j', 'z'] {
+        assert_eq!(menu.handle(Key::Char(key)), Choice::Stay);
+    }
+    assert_eq!(menu.selected, 1);
+
+    assert_eq!(menu.handle(Key::Char('q')), Choice::Back);
+    assert_eq!(menu.handle(Key::Esc), Choice::Back);
+}
+
+#[test]
+fn menu_box_fits_the_longest_line() {
+    let mut menu = test_menu();
+    let (width, height) = text_size(&menu.lines());
+    assert_eq!(width as usize, "│  > x. A LONGER ENTRY  │".chars().count());
+    assert_eq!(height, 7);
+
+    menu.footer.push("a footer wider than any entry".to_string());
+    let lines = menu.lines();
+    assert_eq!(lines.len(), 9);
+    assert!(lines.iter().all(|line| line.chars().count() == lines[0].chars().count()));
+}
+
+#[test]
+fn key_presets_cycle() {
+    let mut keymap = Keymap::default();
+    keymap.next_preset();
+    assert_eq!(keymap.preset, "vim");
+
+    for _ in 0..Keymap::preset_names().count() - 1 {
+        keymap.next_preset();
+    }
+    assert_eq!(keymap.preset, "wasd");
+}
+
+// -------------- charts --------------
+
+#[test]
+fn bar_chart_scales_to_the_largest_value() {
+    let rows = bar_chart(&[0, 1, 5, 10], 4, '#');
+
+    assert_eq!(rows, vec![
+        "10 │   #",
+        "   │   #",
+        "   │  ##",
+        "   │ ###",
+        " 0 ╰────"
+    ]);
+    assert_eq!(bar_chart(&[], 2, '#').len(), 3);
+}
+
+// -------------- help --------------
+
+#[test]
+fn help_lists_the_keys_actually_bound() {
+    let mut keymap = Keymap::preset("vim").unwrap();
+    keymap.rebind(Command::Pause, Key::Char(' '));
+    let lines = help::controls(&keymap);
+
+    assert!(lines.iter().any(|line| line.starts_with("left   h left")));
+    assert!(lines.iter().any(|line| line.starts_with("pause  space")));
+    assert!(lines.iter().any(|line| line.starts_with("help   ?")));
+    assert!(lines.iter().any(|line| line.starts_with("quit   q") && line.contains("asks")));
+}
+
+#[test]
+fn help_rules_follow_the_mode() {
+    let hard = help::rules(Some((Difficulty::Hard, Rules::Walls))).join("\n");
+    assert!(hard.contains("Hard:") && hard.contains("Walls:"));
+    assert!(!hard.contains("Easy:") && !hard.contains("Wrap:"));
+
+    let all = help::rules(None).join("\n");
+    for label in ["Easy:", "Hard:", "Wrap:", "Walls:"] {
+        assert!(all.contains(label));
+    }
+}
+
+#[test]
+fn help_items_fit_their_box() {
+    let lines = help::items();
+    for kind in ITEM_KINDS.iter() {
+        assert!(lines.iter().any(|line| line.contains(kind.name) && line.contains(&kind.effect.describe())));
+    }
+
+    // The red apple's glyph carries a variation selector, which takes no
+    // room on screen.
+    let content = boxed(&lines);
+    let width = layout::width(&content[0]);
+    assert!(content.iter().all(|line| layout::width(line) == width));
+}
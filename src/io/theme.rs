@@ -0,0 +1,303 @@
+// Theme files set the glyphs and colours the game is drawn with. They hold
+// `key: value` lines; lines starting with `#` are comments:
+//
+//     name: Classic
+//     snake: ✿ green
+//     head: ▲▼◀▶
+//     body: ┏┓┗┛━┃
+//     tail: •
+//     wall: █ default
+//     border: ╭╮╰╯─│
+//     label: yellow
+//     title: red
+//     item Red apple: ❤︎ red
+//
+// `border` lists the corners, top left first, then the horizontal and the
+// vertical line. Items are named as in `ITEM_KINDS`; those left out keep
+// their own glyph and colour.
+//
+// The snake is drawn in the colour of `snake`. `head` gives the head facing
+// up, down, left and right, and `body` the pieces joining two segments, in
+// the same order as `border`. Whatever of `head`, `body` and `tail` is left
+// out is drawn with the `snake` glyph.
+//
+// A colour is `default`, one of the 16 names below, a palette index from 0
+// to 255, or `#rrggbb`. Colours the terminal cannot show are replaced with
+// the closest ones it has.
+
+use std::{ collections::HashMap, env, sync::OnceLock };
+use crate::{
+    io::{ screen::Color, storage },
+    game::items::ItemKind
+};
+
+const BUILTIN_THEMES: [&str; 3] = [
+    include_str!("themes/classic.txt"),
+    include_str!("themes/ascii.txt"),
+    include_str!("themes/neon.txt")
+];
+
+const COLOR_NAMES: [&str; 16] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    "light_black", "light_red", "light_green", "light_yellow", "light_blue", "light_magenta", "light_cyan", "light_white"
+];
+
+// What the 16 named colours look like in xterm, to find the closest one.
+const ANSI_RGB: [(u8, u8, u8); 16] = [
+    (0,0,0), (205,0,0), (0,205,0), (205,205,0), (0,0,238), (205,0,205), (0,205,205), (229,229,229),
+    (127,127,127), (255,0,0), (0,255,0), (255,255,0), (92,92,255), (255,0,255), (0,255,255), (255,255,255)
+];
+
+const BOX_CHARS: [char; 6] = ['╭', '╮', '╰', '╯', '─', '│'];
+
+pub(crate) const RESET: &str = "\x1b[39m";
+
+// How many colours the terminal can show.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub(crate) enum Palette {
+    Ansi16,
+    Ansi256,
+    TrueColor
+}
+
+impl Palette {
+    fn detect() -> Palette {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            Palette::TrueColor
+        } else if term.contains("256color") {
+            Palette::Ansi256
+        } else {
+            Palette::Ansi16
+        }
+    }
+
+    // The SGR sequence setting the foreground to `color`, or to the closest
+    // colour this palette has.
+    pub(crate) fn fg(&self, color: Color) -> String {
+        match (color, self) {
+            (Color::Default, _) => RESET.to_string(),
+            (Color::Ansi(n @ 0..=7), _) => format!("\x1b[{}m", 30 + n),
+            (Color::Ansi(n @ 8..=15), _) => format!("\x1b[{}m", 90 + n - 8),
+            (Color::Ansi(n), Palette::Ansi256 | Palette::TrueColor) => format!("\x1b[38;5;{}m", n),
+            (Color::Ansi(n), Palette::Ansi16) => self.fg(Color::Ansi(nearest_ansi16(ansi256_rgb(n)))),
+            (Color::Rgb(r, g, b), Palette::TrueColor) => format!("\x1b[38;2;{};{};{}m", r, g, b),
+            (Color::Rgb(r, g, b), Palette::Ansi256) => self.fg(Color::Ansi(nearest_ansi256((r, g, b)))),
+            (Color::Rgb(r, g, b), Palette::Ansi16) => self.fg(Color::Ansi(nearest_ansi16((r, g, b))))
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+fn nearest_ansi16(rgb: (u8, u8, u8)) -> u8 {
+    (0..16).min_by_key(|&n| distance(rgb, ANSI_RGB[n as usize])).unwrap()
+}
+
+// Palette entries 16 to 231 are a 6x6x6 colour cube, 232 to 255 a grey ramp.
+fn ansi256_rgb(n: u8) -> (u8, u8, u8) {
+    let level = |i: u8| if i == 0 { 0 } else { 55 + i * 40 };
+
+    match n {
+        0..=15 => ANSI_RGB[n as usize],
+        16..=231 => {
+            let i = n - 16;
+            (level(i / 36), level(i / 6 % 6), level(i % 6))
+        },
+        _ => {
+            let grey = 8 + (n - 232) * 10;
+            (grey, grey, grey)
+        }
+    }
+}
+
+fn nearest_ansi256(rgb: (u8, u8, u8)) -> u8 {
+    (16..=255).min_by_key(|&n| distance(rgb, ansi256_rgb(n))).unwrap()
+}
+
+fn parse_color(text: &str) -> Result<Color, String> {
+    if text == "default" {
+        return Ok(Color::Default);
+    }
+    if let Some(n) = COLOR_NAMES.iter().position(|name| *name == text) {
+        return Ok(Color::Ansi(n as u8));
+    }
+    if let Ok(n) = text.parse::<u8>() {
+        return Ok(Color::Ansi(n));
+    }
+
+    let hex = text.strip_prefix('#').filter(|hex| hex.len() == 6).ok_or(format!("unknown colour '{}'", text))?;
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("unknown colour '{}'", text));
+
+    Ok(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+// Exactly `N` characters, one glyph each.
+fn glyphs<const N: usize>(key: &str, value: &str) -> Result<[char; N], String> {
+    let chars: Vec<char> = value.chars().collect();
+    chars.try_into().map_err(|_| format!("{} needs {} characters, got '{}'", key.trim(), N, value))
+}
+
+// A glyph and the colour it is drawn in.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Style {
+    pub(crate) glyph: String,
+    pub(crate) color: Color
+}
+
+impl Style {
+    fn parse(text: &str) -> Result<Style, String> {
+        let mut parts = text.split_whitespace();
+        let glyph = parts.next().ok_or("missing glyph")?.to_string();
+        let color = parts.next().map_or(Ok(Color::Default), parse_color)?;
+
+        Ok(Style { glyph, color })
+    }
+
+    // The glyph in its colour, ready to be written.
+    pub(crate) fn paint(&self) -> String {
+        format!("{}{}{}", palette().fg(self.color), self.glyph, RESET)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Theme {
+    pub(crate) name: String,
+    pub(crate) snake: Style,
+    pub(crate) head: Option<[char; 4]>,
+    pub(crate) body: Option<[char; 6]>,
+    pub(crate) tail: Option<char>,
+    pub(crate) wall: Style,
+    pub(crate) border: [char; 6],
+    // Labels in the info panel and the score board.
+    pub(crate) label: Color,
+    pub(crate) title: Color,
+    items: HashMap<String, Style>
+}
+
+impl Theme {
+    pub(crate) fn parse(text: &str) -> Result<Theme, String> {
+        let mut name = None;
+        let mut snake = None;
+        let mut head = None;
+        let mut body = None;
+        let mut tail = None;
+        let mut wall = None;
+        let mut border = BOX_CHARS;
+        let mut label = Color::Default;
+        let mut title = Color::Default;
+        let mut items = HashMap::new();
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once(':').ok_or(format!("invalid line '{}'", line))?;
+            let value = value.trim();
+
+            match key.trim() {
+                "name" => name = Some(value.to_string()),
+                "snake" => snake = Some(Style::parse(value)?),
+                "wall" => wall = Some(Style::parse(value)?),
+                "border" => border = glyphs(key, value)?,
+                "head" => head = Some(glyphs(key, value)?),
+                "body" => body = Some(glyphs(key, value)?),
+                "tail" => tail = Some(glyphs::<1>(key, value)?[0]),
+                "label" => label = parse_color(value)?,
+                "title" => title = parse_color(value)?,
+                key => match key.strip_prefix("item ") {
+                    Some(item) => { items.insert(item.trim().to_string(), Style::parse(value)?); },
+                    None => return Err(format!("unknown key '{}'", key))
+                }
+            }
+        }
+
+        Ok(Theme {
+            name: name.ok_or("missing 'name'")?,
+            snake: snake.ok_or("missing 'snake'")?,
+            head,
+            body,
+            tail,
+            wall: wall.ok_or("missing 'wall'")?,
+            border,
+            label,
+            title,
+            items
+        })
+    }
+
+    pub(crate) fn builtin() -> Vec<Theme> {
+        BUILTIN_THEMES.iter().map(|text| Theme::parse(text).unwrap()).collect()
+    }
+
+    // Built-in themes followed by those found in the `themes` directory
+    // under `storage::data_dir()`.
+    pub(crate) fn all() -> Vec<Theme> {
+        let mut themes = Theme::builtin();
+        themes.extend(storage::load_dir(&storage::data_dir().join("themes"), Theme::parse));
+        themes
+    }
+
+    // The theme named `name`, ignoring case.
+    pub(crate) fn find(name: &str) -> Option<Theme> {
+        Theme::all().into_iter().find(|theme| theme.name.eq_ignore_ascii_case(name))
+    }
+
+    // Classic where the locale says the terminal speaks UTF-8, ASCII
+    // elsewhere.
+    fn default_for_terminal() -> Theme {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+            .to_lowercase();
+        let utf8 = locale.contains("utf-8") || locale.contains("utf8");
+
+        Theme::find(if utf8 { "Classic" } else { "ASCII" }).unwrap()
+    }
+
+    pub(crate) fn item(&self, kind: &ItemKind) -> Style {
+        self.items.get(kind.name).cloned().unwrap_or_else(|| Style {
+            glyph: kind.glyph.to_string(),
+            color: kind.color.color()
+        })
+    }
+
+    // `text` with the box drawing characters of the `*_SCREEN` panels
+    // replaced by this theme's border.
+    pub(crate) fn boxes(&self, text: &str) -> String {
+        if self.border == BOX_CHARS {
+            return text.to_string();
+        }
+
+        text.chars()
+            .map(|ch| match BOX_CHARS.iter().position(|c| *c == ch) {
+                Some(i) => self.border[i],
+                None => ch
+            })
+            .collect()
+    }
+}
+
+static CURRENT: OnceLock<Theme> = OnceLock::new();
+static PALETTE: OnceLock<Palette> = OnceLock::new();
+
+// Chooses the theme for the whole run. Only the first call counts, and
+// only when nothing has been drawn yet.
+pub(crate) fn init(theme: Theme) {
+    let _ = CURRENT.set(theme);
+}
+
+pub(crate) fn current() -> &'static Theme {
+    CURRENT.get_or_init(Theme::default_for_terminal)
+}
+
+pub(crate) fn palette() -> Palette {
+    *PALETTE.get_or_init(Palette::detect)
+}
@@ -17,9 +17,13 @@
 mod game;
 mod menu;
 mod io;
+mod cli;
 
-use menu::MenuAction;
-use game::app::App;
+use clap::Parser;
+use cli::{ Cli, CliCommand };
+use menu::{ MenuAction, GameOptions, custom::Presets };
+use game::{ app::App, level::Level, scores::{ ScoreBoard, ScoreKey, Mode, MAX_NAME_LEN }, stats::Stats };
+use io::{ theme::{ self, Theme }, keymap::Keymap, renderable::ask_name };
 
 use termion::{
     raw::IntoRawMode,
@@ -29,16 +33,84 @@ use termion::{
 };
 
 use std::{
+    env,
+    fmt,
+    process,
+    path::Path,
     io::{ stdout, Write }
 };
 
 
 fn main() {
+    let cli = Cli::parse();
+    let scores_path = cli.scores_path();
+
+    match cli.command {
+        Some(CliCommand::Scores { json }) => {
+            let score_board = ScoreBoard::load(&scores_path).unwrap_or_else(|err| fail_reading(&scores_path, err));
+            if json {
+                println!("{}", score_board.to_json());
+            } else {
+                print!("{}", score_board);
+            }
+            return;
+        },
+        Some(CliCommand::ResetScores) => {
+            if let Err(err) = ScoreBoard::new().save(&scores_path) {
+                eprintln!("{}: {}", scores_path.display(), err);
+                process::exit(1);
+            }
+            println!("Scores in {} reset", scores_path.display());
+            return;
+        },
+        None => {}
+    }
+
+    // A theme asked for on the command line has to exist; one from the
+    // environment is only a preference.
+    match &cli.theme {
+        Some(name) => theme::init(Theme::find(name).unwrap_or_else(|| cli::fail(format!("unknown theme '{}'", name)))),
+        None => if let Some(theme) = env::var("SNAKE_THEME").ok().and_then(|name| Theme::find(&name)) {
+            theme::init(theme);
+        }
+    }
+
+    let mut keymap = match &cli.keymap {
+        Some(name) => Keymap::preset(name).map_or_else(|| Keymap::read(Path::new(name)), Ok).unwrap_or_else(|err| cli::fail(err)),
+        None => Keymap::load()
+    };
+
+    let levels = Level::all();
+    let level = cli.level.as_ref().map(|name| {
+        levels.iter()
+            .position(|level| level.name.eq_ignore_ascii_case(name))
+            .unwrap_or_else(|| cli::fail(format!("unknown level '{}'", name)))
+    });
+
+    // The game asked for on the command line, played instead of the menu.
+    let mut start = cli.starts_game().then_some(match level {
+        Some(index) => MenuAction::StartLevel(cli.difficulty, index),
+        None => MenuAction::StartGame(GameOptions::new(cli.difficulty, cli.size, cli.rules))
+    });
+
+    // Problems with the score file are reported once the terminal is back
+    // to normal, unless it cannot be read at all: saving over it could lose
+    // scores that are fine.
+    let (mut score_board, warning) = ScoreBoard::load_or_recover(&scores_path).unwrap_or_else(|err| fail_reading(&scores_path, err));
+    let mut save_error = None;
+    let stats_path = Stats::path_for(&scores_path);
+    let (mut stats, stats_warning) = Stats::load_or_recover(&stats_path).unwrap_or_else(|err| fail_reading(&stats_path, err));
+    let presets_path = Presets::default_path();
+    let (mut presets, presets_warning) = Presets::load_or_recover(&presets_path).unwrap_or_else(|err| fail_reading(&presets_path, err));
+    let mut stats_error = None;
+
+    // The name offered when a game makes the leaderboard: the last one
+    // entered, or the login name to begin with.
+    let mut name: String = env::var("USER").unwrap_or_default().chars().take(MAX_NAME_LEN).collect();
 
     let stdout = stdout();
     let mut stdin = async_stdin();
     let mut stdout = stdout.lock().into_raw_mode().unwrap();
-    let mut score_board = game::scores::ScoreBoard::load();
 
 
     stdout.activate_raw_mode().unwrap();
@@ -46,15 +118,60 @@ fn main() {
 
     
     loop {
-        let choice = menu::run(&mut stdin, &mut stdout, score_board);
-        match choice {
+        let choice = match start.take() {
+            Some(choice) => choice,
+            None => menu::run(&mut stdin, &mut stdout, &score_board, &stats, &levels, &mut keymap, &mut presets)
+        };
+        let seed = cli.seed.unwrap_or_else(rand::random);
+
+        let (key, (mut record, ending)) = match choice {
             MenuAction::Quit => break,
-            MenuAction::StartGame(difficulty, size) => {
-                let score = App::run(&mut stdin, &mut stdout, difficulty, size);
-                score_board = score_board.update(score, choice);
-                score_board.save();
+            MenuAction::StartGame(options) => {
+                // Scores go with the field the game started on.
+                let app = App::new(&options, None, seed);
+                let mode = Mode { rules: options.rules, difficulty: options.difficulty, size: Some(app.state.frame.field()) };
+                (ScoreKey::Mode(mode), app.run(&mut stdin, &mut stdout, &mut keymap))
+            },
+            MenuAction::StartLevel(difficulty, index) => {
+                let level = &levels[index];
+                let record = App::new(&GameOptions::new(difficulty, None, level.rules()), Some(level), seed).run(&mut stdin, &mut stdout, &mut keymap);
+                (ScoreKey::Level(&level.name), record)
             }
+        };
+
+        stats.record(key, &record, ending);
+        stats_error = stats.save(&stats_path).err().or(stats_error);
+
+        if score_board.qualifies(key, record.score) {
+            name = ask_name(&mut stdin, &mut stdout, &name);
+            record.name = name.clone();
+        }
+
+        score_board = score_board.update(key, record);
+        save_error = score_board.save(&scores_path).err().or(save_error);
+
+        if cli.starts_game() {
+            break;
         }
     }
-    write!(stdout, "{}", cursor::Show).unwrap();
+    write!(stdout, "{}{}{}", clear::All, cursor::Goto(1, 1), cursor::Show).unwrap();
+    drop(stdout);
+
+    for warning in [warning, stats_warning, presets_warning].into_iter().flatten() {
+        eprintln!("{}", warning);
+    }
+    if let Some(err) = save_error {
+        eprintln!("Could not save scores to {}: {}", scores_path.display(), err);
+    }
+    if let Some(err) = stats_error {
+        eprintln!("Could not save statistics to {}: {}", stats_path.display(), err);
+    }
+    if let Some(err) = &keymap.save_error {
+        eprintln!("Could not save key bindings to {}: {}", keymap.file.display(), err);
+    }
+}
+
+fn fail_reading(path: &Path, err: impl fmt::Display) -> ! {
+    eprintln!("{}: {}", path.display(), err);
+    process::exit(1);
 }
@@ -0,0 +1,233 @@
+use std::{ io::{ self, Write }, path::{ Path, PathBuf } };
+use serde::{ Serialize, Deserialize };
+use termion::{ AsyncReader, terminal_size, clear, event::Key };
+
+use crate::io::renderable::{ Frame, Renderable, CenteredPanel, boxed, ask_text };
+use crate::io::layout::{ text_size, wait_keys_fitting };
+use crate::io::storage;
+use crate::menu::{ Difficulty, Rules, GameOptions };
+
+pub(crate) const PRESETS_FILE: &str = "presets.json";
+
+// How many presets the main menu has room for.
+pub(crate) const MAX_PRESETS: usize = 5;
+
+// The fields of the custom game screen, top to bottom.
+const FIELDS: [&str; 6] = ["width", "height", "difficulty", "rules", "speed", "length"];
+
+// Numbers typed into a field stop growing here.
+const MAX_TYPED: u64 = 999;
+
+// A custom game saved under a name.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Preset {
+    pub(crate) name: String,
+    pub(crate) options: GameOptions
+}
+
+// The saved presets, oldest first, and the file they are kept in.
+pub(crate) struct Presets {
+    pub(crate) list: Vec<Preset>,
+    file: PathBuf
+}
+
+impl Presets {
+    // Where presets are kept, next to the scores.
+    pub(crate) fn default_path() -> PathBuf {
+        storage::data_dir().join(PRESETS_FILE)
+    }
+
+    // The presets in `path`, none when there is no file yet. A file that
+    // does not parse is moved aside to `<path>.bak`, with a message saying
+    // so, rather than saved over with the next preset.
+    pub(crate) fn load_or_recover(path: &Path) -> io::Result<(Presets, Option<String>)> {
+        let (list, warning) = storage::load_or_back_up(path, |text| Ok(serde_json::from_str(text)?))?;
+        let presets = Presets { list: list.unwrap_or_default(), file: path.to_path_buf() };
+
+        Ok((presets, warning.map(|warning| format!("{}, starting with no presets", warning))))
+    }
+
+    pub(crate) fn save(&self) -> io::Result<()> {
+        storage::write_atomic(&self.file, serde_json::to_string_pretty(&self.list).unwrap().as_bytes())
+    }
+
+    // Replaces the preset with the same name, or adds a new one, dropping
+    // the oldest when there are already `MAX_PRESETS`.
+    pub(crate) fn add(&mut self, preset: Preset) {
+        self.list.retain(|other| other.name != preset.name);
+        if self.list.len() == MAX_PRESETS {
+            self.list.remove(0);
+        }
+        self.list.push(preset);
+    }
+}
+
+// Width, height, speed and length; the rest are toggles.
+fn is_number(field: usize) -> bool {
+    !matches!(field, 2 | 3)
+}
+
+fn field_value(options: &GameOptions, field: usize) -> String {
+    let (w, h) = options.size.unwrap_or_default();
+
+    match field {
+        0 => w.to_string(),
+        1 => h.to_string(),
+        2 => options.difficulty.to_string(),
+        3 => options.rules.to_string(),
+        4 => options.speed.to_string(),
+        _ => options.length.to_string()
+    }
+}
+
+// Applies `change` to the number in `field`; the other fields only toggle.
+fn edit_field<F: Fn(u64) -> u64>(options: &mut GameOptions, field: usize, change: F) {
+    let (w, h) = options.size.unwrap_or_default();
+    let clamp = |value: u64| change(value).min(MAX_TYPED) as u16;
+
+    match field {
+        0 => options.size = Some((clamp(w as u64), h)),
+        1 => options.size = Some((w, clamp(h as u64))),
+        2 => options.difficulty = match options.difficulty {
+            Difficulty::Easy => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy
+        },
+        3 => options.rules = match options.rules {
+            Rules::Wrap => Rules::Walls,
+            Rules::Walls => Rules::Wrap
+        },
+        4 => options.speed = change(options.speed).min(MAX_TYPED),
+        _ => options.length = clamp(options.length as u64)
+    }
+}
+
+// The lines of the form, for a terminal of `window`.
+fn form_lines(options: &GameOptions, selected: usize, message: &str, window: (u16, u16)) -> Vec<String> {
+    let mut lines = vec![
+        "CUSTOM GAME".to_string(),
+        String::new()
+    ];
+
+    for (index, name) in FIELDS.iter().enumerate() {
+        let marker = if index == selected { '>' } else { ' ' };
+        lines.push(format!("{} {:10} {}", marker, name, field_value(options, index)));
+    }
+
+    lines.push(String::new());
+    lines.push(match (options.check(window), message.is_empty()) {
+        (Err(err), _) => err,
+        (Ok(()), true) => format!("window: {}x{}", window.0, window.1),
+        (Ok(()), false) => message.to_string()
+    });
+    lines.push(String::new());
+    lines.push("up/down: field  left/right: change".to_string());
+    lines.push("enter: play  +: save preset  esc: back".to_string());
+
+    boxed(&lines)
+}
+
+// The form for a game of any size, speed and length. Fields are picked with
+// the up and down arrows and changed with left and right, or by typing a
+// number; like the menus, the form does not follow the key bindings. Enter
+// starts the game once the values fit the window; `+` saves them as a
+// preset. Returns `None` when the player backs out with esc or `q`.
+pub(crate) fn custom_game<W: Write>(stdin: &mut AsyncReader, stdout: &mut W, presets: &mut Presets, start: GameOptions) -> Option<GameOptions> {
+    let mut options = start;
+    let mut selected = 0;
+    // Whether a digit typed now starts a new number rather than adding to
+    // the one in the field.
+    let mut fresh = true;
+    let mut message = String::new();
+
+    loop {
+        let needed = text_size(&form_lines(&options, selected, &message, terminal_size().unwrap()));
+        let keys = wait_keys_fitting(stdin, stdout, needed, |stdout| {
+            let window = terminal_size().unwrap();
+            let content = form_lines(&options, selected, &message, window);
+            write!(stdout, "{}", clear::All).unwrap();
+            CenteredPanel { content: content.iter().map(String::as_str).collect(), frame: Frame::new((1, 1), window) }.render(stdout);
+            stdout.flush().unwrap();
+        });
+        message.clear();
+
+        for key in keys {
+            match key {
+                Key::Up => { selected = (selected + FIELDS.len() - 1) % FIELDS.len(); fresh = true; },
+                Key::Down => { selected = (selected + 1) % FIELDS.len(); fresh = true; },
+                Key::Left => edit_field(&mut options, selected, |value| value.saturating_sub(1)),
+                Key::Right => edit_field(&mut options, selected, |value| value + 1),
+                Key::Char(c) if c.is_ascii_digit() && is_number(selected) => {
+                    let digit = c.to_digit(10).unwrap() as u64;
+                    edit_field(&mut options, selected, |value| if fresh { digit } else { value * 10 + digit });
+                    fresh = false;
+                },
+                Key::Backspace if is_number(selected) => edit_field(&mut options, selected, |value| value / 10),
+                Key::Char('\n') if options.check(terminal_size().unwrap()).is_ok() => return Some(options),
+                Key::Char('+') => {
+                    if let Some(name) = ask_text(stdin, stdout, "SAVE PRESET", "Preset name:", "") {
+                        presets.add(Preset { name: name.clone(), options: options.clone() });
+                        message = match presets.save() {
+                            Ok(()) => format!("saved as {}", name),
+                            Err(err) => format!("not saved: {}", err)
+                        };
+                    }
+                },
+                Key::Esc | Key::Char('q') => return None,
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ env, fs, process };
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("snake-{}-{}", process::id(), name))
+    }
+
+    #[test]
+    fn the_custom_game_form_starts_from_a_size_that_fits() {
+        for window in [(80, 24), (100, 40), (50, 12)] {
+            assert_eq!(GameOptions::custom_start(window).check(window), Ok(()));
+        }
+        assert_eq!(GameOptions::custom_start((80, 24)).size, Some((76, 19)));
+    }
+
+    #[test]
+    fn presets_are_saved_and_capped() {
+        let path = temp_path("presets.json");
+        let load = |path| Presets::load_or_recover(path).unwrap().0;
+        let mut presets = load(&path);
+        let options = GameOptions::new(Difficulty::Easy, Some((50, 20)), Rules::Wrap);
+
+        for index in 0..=MAX_PRESETS {
+            presets.add(Preset { name: format!("p{}", index), options: options.clone() });
+        }
+        presets.add(Preset { name: "p3".to_string(), options: GameOptions { speed: 15, ..options } });
+        presets.save().unwrap();
+
+        let names: Vec<String> = load(&path).list.into_iter().map(|preset| preset.name).collect();
+        assert_eq!(names, ["p1", "p2", "p4", "p5", "p3"]);
+        assert_eq!(load(&path).list[4].options.speed, 15);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn corrupt_presets_are_backed_up() {
+        let path = temp_path("bad-presets.json");
+        fs::write(&path, "[{").unwrap();
+
+        let (presets, warning) = Presets::load_or_recover(&path).unwrap();
+        let backup = temp_path("bad-presets.json.bak");
+
+        assert!(presets.list.is_empty());
+        assert!(warning.unwrap().contains("no presets"));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "[{");
+
+        fs::remove_file(backup).unwrap();
+    }
+}
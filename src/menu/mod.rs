@@ -1,90 +1,559 @@
-use std::io::{ Write };
+use std::{ fmt, io::Write };
 use serde::{ Serialize, Deserialize };
+use clap::ValueEnum;
 
-use termion::{ AsyncReader, terminal_size, cursor, clear };
+use termion::{ AsyncReader, terminal_size, cursor, clear, event::Key };
 
 use crate::io::renderable::{
     Frame,
     Renderable,
     CenteredPanel,
-    MAIN_MENU_SCREEN
+    boxed,
+    bar_chart,
+    rebind_keys
 };
+use crate::io::keymap::Keymap;
+use crate::io::storage;
+use crate::io::help;
+use crate::io::theme;
 
-use crate::io::wait_char;
-use crate::game::scores::ScoreBoard;
+use crate::io::layout::{ text_size, wait_char_fitting, wait_keys_fitting };
+use crate::game::scores::{ ScoreBoard, LBScore, SCORE_BOARD_ROWS };
+use crate::game::stats::Stats;
+use crate::game::level::{ Level, START_LENGTH };
+use crate::game::app::{ window_for, field_for, MIN_SIZE };
+use crate::game::state::START_SPEED;
 
-#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) mod custom;
+pub(crate) mod widget;
+
+use custom::{ Presets, custom_game };
+use widget::{ Menu, MenuItem, Choice };
+
+// Ticks per second.
+pub(crate) const MAX_SPEED: u64 = 30;
+
+pub(crate) const MAX_LENGTH: u16 = 50;
+
+// Main menu keys for the presets, in the order they were saved.
+const PRESET_KEYS: [char; custom::MAX_PRESETS] = ['a', 'b', 'd', 'e', 'f'];
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize, ValueEnum)]
 pub(crate) enum Difficulty {
     Easy,
     Hard
 }
 
-impl ToString for Difficulty {
-    fn to_string(&self) -> String {
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Hard => write!(f, "Hard")
+        }
+    }
+}
+
+// What happens when the snake reaches the border of the field.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Serialize, Deserialize, ValueEnum)]
+pub(crate) enum Rules {
+    #[default]
+    Wrap,
+    Walls
+}
+
+impl fmt::Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Difficulty::Easy => "Easy".to_string(),
-            Difficulty::Hard => "Hard".to_string()
+            Rules::Wrap => write!(f, "Wrap"),
+            Rules::Walls => write!(f, "Walls")
+        }
+    }
+}
+
+// How a game on the open field is set up.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub(crate) struct GameOptions {
+    pub(crate) difficulty: Difficulty,
+    pub(crate) rules: Rules,
+    // The size of the field, border left out, or `None` to follow the
+    // window.
+    pub(crate) size: Option<(u16, u16)>,
+    pub(crate) speed: u64,
+    pub(crate) length: u16
+}
+
+impl GameOptions {
+    pub(crate) fn new(difficulty: Difficulty, size: Option<(u16, u16)>, rules: Rules) -> GameOptions {
+        GameOptions { difficulty, rules, size, speed: START_SPEED, length: START_LENGTH as u16 }
+    }
+
+    // What the custom game form starts from: the fixed size, or as much of
+    // it as `window` has room for.
+    pub(crate) fn custom_start(window: (u16, u16)) -> GameOptions {
+        let (w, h) = field_for(window);
+        let size = (w.clamp(MIN_SIZE.0, FIXED_SIZE.0), h.clamp(MIN_SIZE.1, FIXED_SIZE.1));
+        GameOptions::new(Difficulty::Easy, Some(size), Rules::Wrap)
+    }
+
+    // Why a game with these options cannot be played in a `window` sized
+    // terminal, if it cannot.
+    pub(crate) fn check(&self, window: (u16, u16)) -> Result<(), String> {
+        if let Some(size) = self.size {
+            if size.0 < MIN_SIZE.0 || size.1 < MIN_SIZE.1 {
+                return Err(format!("size: at least {}x{}", MIN_SIZE.0, MIN_SIZE.1));
+            }
+
+            let needed = window_for(size);
+            if needed.0 > window.0 || needed.1 > window.1 {
+                let most = field_for(window);
+                return Err(format!("size: {}x{} at most in this window", most.0, most.1));
+            }
+        }
+
+        if !(1..=MAX_SPEED).contains(&self.speed) {
+            return Err(format!("speed: from 1 to {}", MAX_SPEED));
         }
+
+        // Half the width of the field, so the snake has room to turn.
+        let field_width = self.size.unwrap_or(field_for(window)).0;
+        let max_length = (field_width / 2).min(MAX_LENGTH);
+        if !(2..=max_length).contains(&self.length) {
+            return Err(format!("length: from 2 to {}", max_length));
+        }
+
+        Ok(())
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, PartialEq)]
 pub(crate) enum MenuAction {
-    StartGame(Difficulty, Option<(u16, u16)>),
+    StartGame(GameOptions),
+    // Index into the list of levels handed to `run`.
+    StartLevel(Difficulty, usize),
     Quit
 }
 
 
 
-impl ToString for MenuAction {
-    fn to_string(&self) -> String {
+impl fmt::Display for MenuAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            MenuAction::StartGame(difficulty, size) => {
-                let size = match size {
+            MenuAction::StartGame(options) => {
+                let size = match options.size {
                     Some((w,h)) => format!("{}x{}", w, h),
                     None => "Full".to_string()
                 };
-                format!("Start {} mode ({})", difficulty.to_string(), size)
+                write!(f, "Start {} {} mode ({})", options.difficulty, options.rules, size)
             },
-            MenuAction::Quit => "Quit".to_string()
+            MenuAction::StartLevel(difficulty, index) => write!(f, "Start {} level #{}", difficulty, index + 1),
+            MenuAction::Quit => write!(f, "Quit")
         }
     }
 }
 
 
-pub(crate) fn run<W:Write>(stdin:&mut AsyncReader, stdout:&mut W, score_board: ScoreBoard) -> MenuAction {
-    let panel = CenteredPanel {
-        content: MAIN_MENU_SCREEN.to_vec(),
-        frame: Frame::new((1,1), terminal_size().unwrap())
-    };
-    write!(stdout, "{}{}", clear::All, cursor::Goto(1,1)).unwrap();
-    score_board.render(stdout);
+// Everything the menus lead to.
+#[derive(Clone)]
+enum Entry {
+    Play,
+    Settings,
+    Scores,
+    Help,
+    Start(GameOptions),
+    Custom,
+    // Index into the saved presets.
+    Preset(usize),
+    Levels,
+    // Index into the levels handed to `run`.
+    Level(usize),
+    LevelDifficulty,
+    // Index into the leaderboards that have games.
+    Table(usize),
+    Stats,
+    Keys,
+    KeyPreset,
+    // Index into the help pages.
+    HelpPage(usize),
+    About,
+    Quit
+}
+
+// The open field games of the play menu, under keys 1 to 8, and whether
+// each is played on a `FIXED_SIZE` field rather than one filling the window.
+const GAMES: [(Difficulty, Rules, bool); 8] = [
+    (Difficulty::Easy, Rules::Wrap, false),
+    (Difficulty::Hard, Rules::Wrap, false),
+    (Difficulty::Easy, Rules::Wrap, true),
+    (Difficulty::Hard, Rules::Wrap, true),
+    (Difficulty::Easy, Rules::Walls, false),
+    (Difficulty::Hard, Rules::Walls, false),
+    (Difficulty::Easy, Rules::Walls, true),
+    (Difficulty::Hard, Rules::Walls, true)
+];
+
+const FIXED_SIZE: (u16, u16) = (76, 21);
+
+// Height of the score chart on the statistics screen.
+const STATS_CHART_ROWS: usize = 6;
+
+const HINT: &str = "arrows: move  enter: pick  esc: back";
+
+fn main_menu() -> Menu<Entry> {
+    Menu::new("SNAKE", vec![
+        MenuItem::new(Some('p'), "PLAY", Entry::Play),
+        MenuItem::new(Some('s'), "SETTINGS", Entry::Settings),
+        MenuItem::new(Some('t'), "SCORES", Entry::Scores),
+        MenuItem::new(Some('h'), "HELP", Entry::Help),
+        MenuItem::new(Some('q'), "QUIT", Entry::Quit)
+    ])
+}
+
+// The games on offer, with the presets listed under the custom game.
+fn play_menu(presets: &Presets) -> Menu<Entry> {
+    let mut items: Vec<MenuItem<Entry>> = GAMES.iter().enumerate()
+        .map(|(index, (difficulty, rules, fixed))| {
+            let size = fixed.then_some(FIXED_SIZE);
+            let kind = match rules { Rules::Wrap => "MODE", Rules::Walls => "WALLS" };
+            let shown = size.map_or(String::new(), |(w, h)| format!(" {}x{}", w, h));
+            let label = format!("{} {}{}", difficulty.to_string().to_uppercase(), kind, shown);
+            let key = char::from_digit(index as u32 + 1, 10);
+            MenuItem::new(key, &label, Entry::Start(GameOptions::new(*difficulty, size, *rules)))
+        })
+        .collect();
+
+    items.push(MenuItem::new(Some('c'), "CUSTOM GAME", Entry::Custom));
+    for (index, (key, preset)) in PRESET_KEYS.iter().zip(&presets.list).enumerate() {
+        items.push(MenuItem::new(Some(*key), &preset.name.to_uppercase(), Entry::Preset(index)));
+    }
+    items.push(MenuItem::new(Some('l'), "LEVELS", Entry::Levels));
+    items.push(MenuItem::back());
+
+    Menu::new("PLAY", items)
+}
+
+fn settings_menu(keymap: &Keymap) -> Menu<Entry> {
+    Menu::new("SETTINGS", vec![
+        MenuItem::new(Some('k'), "KEYS", Entry::Keys),
+        MenuItem::new(Some('p'), &format!("KEY PRESET: {}", keymap.preset), Entry::KeyPreset),
+        MenuItem::back()
+    ])
+}
+
+// The statistics, then one entry for each leaderboard with games, the first
+// ten with a digit.
+fn scores_menu(score_board: &ScoreBoard) -> Menu<Entry> {
+    let tables = played_tables(score_board);
+    let mut items = vec![MenuItem::new(Some('s'), "STATISTICS", Entry::Stats)];
+    items.extend(tables.iter().enumerate()
+        .map(|(index, (title, score))| {
+            let key = char::from_digit((index as u32 + 1) % 10, 10).filter(|_| index < 10);
+            MenuItem::new(key, &format!("{}  best: {}", title, score.best()), Entry::Table(index))
+        }));
+    items.push(MenuItem::back());
+
+    let mut menu = Menu::new("SCORES", items);
+    if tables.is_empty() {
+        menu.footer.push("No games yet".to_string());
+    }
+    menu
+}
+
+fn help_menu() -> Menu<Entry> {
+    let mut items: Vec<MenuItem<Entry>> = help::PAGES.iter().enumerate()
+        .map(|(index, page)| MenuItem::new(page.chars().next().map(|c| c.to_ascii_lowercase()), page, Entry::HelpPage(index)))
+        .collect();
+    items.push(MenuItem::new(Some('a'), "ABOUT", Entry::About));
+    items.push(MenuItem::back());
+
+    Menu::new("HELP", items)
+}
 
-    panel.render(stdout);
-    stdout.flush().unwrap();
+// Shows `menu` until an entry is picked, or the player backs out. The main
+// menu has the score board above it.
+fn choose<W:Write>(stdin:&mut AsyncReader, stdout:&mut W, menu: &mut Menu<Entry>, score_board: Option<&ScoreBoard>) -> Option<Entry> {
+    let top = if score_board.is_some() { SCORE_BOARD_ROWS } else { 0 };
 
     loop {
+        let (menu_w, menu_h) = text_size(&menu.lines());
+        let needed = (menu_w.max(score_board.map_or(0, |_| ScoreBoard::width())), top + menu_h);
 
-        let char = wait_char(stdin);
+        let keys = wait_keys_fitting(stdin, stdout, needed, |stdout| {
+            let (w, h) = terminal_size().unwrap();
+            write!(stdout, "{}{}", clear::All, cursor::Goto(1,1)).unwrap();
+            if let Some(score_board) = score_board {
+                score_board.render(stdout);
+            }
+            menu.render(stdout, Frame::new((1, top + 1), (w, h.saturating_sub(top))));
+            stdout.flush().unwrap();
+        });
 
-        match char {
-            b'1' => {
-                return MenuAction::StartGame(Difficulty::Easy, None);
-            },
-            b'2' => {
-                return MenuAction::StartGame(Difficulty::Hard, None)
-            },
-            b'3' => {
-                return MenuAction::StartGame(Difficulty::Easy, Some((80,25)));
-            },
-            b'4' => {
-                return MenuAction::StartGame(Difficulty::Hard, Some((80,25)));
-            },
-            b'q' => {
-                return MenuAction::Quit;
+        for key in keys {
+            match menu.handle(key) {
+                Choice::Pick(entry) => return Some(entry),
+                Choice::Back => return None,
+                Choice::Stay => ()
+            }
+        }
+    }
+}
+
+// Shows the submenu returned by `build` until an entry is picked, keeping
+// the highlighted one when the menu is built again after a change.
+fn choose_from<W:Write, F: Fn() -> Menu<Entry>>(stdin:&mut AsyncReader, stdout:&mut W, selected: &mut usize, build: F) -> Option<Entry> {
+    let mut menu = build();
+    menu.selected = (*selected).min(menu.items.len().saturating_sub(1));
+    if !menu.footer.is_empty() {
+        menu.footer.push(String::new());
+    }
+    menu.footer.push(HINT.to_string());
+    let entry = choose(stdin, stdout, &mut menu, None);
+    *selected = menu.selected;
+    entry
+}
+
+// The main menu and the ones under it. Quits from the main menu on `q` and
+// esc.
+pub(crate) fn run<W:Write>(stdin:&mut AsyncReader, stdout:&mut W, score_board: &ScoreBoard, stats: &Stats, levels: &[Level], keymap: &mut Keymap, presets: &mut Presets) -> MenuAction {
+    let mut main = main_menu();
+
+    loop {
+        let entry = match choose(stdin, stdout, &mut main, Some(score_board)) {
+            None | Some(Entry::Quit) => return MenuAction::Quit,
+            Some(entry) => entry
+        };
+
+        // Each submenu stays open until the player backs out of it.
+        let mut selected = 0;
+        loop {
+            let entry = match entry {
+                Entry::Play => choose_from(stdin, stdout, &mut selected, || play_menu(presets)),
+                Entry::Settings => choose_from(stdin, stdout, &mut selected, || settings_menu(keymap)),
+                Entry::Scores => choose_from(stdin, stdout, &mut selected, || scores_menu(score_board)),
+                _ => choose_from(stdin, stdout, &mut selected, help_menu)
+            };
+
+            let Some(entry) = entry else { break };
+            if let Entry::Stats = entry {
+                show_stats(stdin, stdout, stats);
+            } else if let Some(action) = open(stdin, stdout, entry, score_board, levels, keymap, presets) {
+                return action;
+            }
+        }
+    }
+}
+
+// Does what a submenu entry stands for. Returns the game to start, if any.
+fn open<W:Write>(stdin:&mut AsyncReader, stdout:&mut W, entry: Entry, score_board: &ScoreBoard, levels: &[Level], keymap: &mut Keymap, presets: &mut Presets) -> Option<MenuAction> {
+    match entry {
+        Entry::Start(options) => Some(MenuAction::StartGame(options)),
+        Entry::Custom => {
+            let start = GameOptions::custom_start(terminal_size().unwrap());
+            custom_game(stdin, stdout, presets, start).map(MenuAction::StartGame)
+        },
+        Entry::Preset(index) => {
+            // A preset made for a bigger window opens in the form, which
+            // says what does not fit.
+            let options = presets.list[index].options.clone();
+            if options.check(terminal_size().unwrap()).is_ok() {
+                return Some(MenuAction::StartGame(options));
+            }
+            custom_game(stdin, stdout, presets, options).map(MenuAction::StartGame)
+        },
+        Entry::Levels => pick_level(stdin, stdout, score_board, levels),
+        Entry::Table(page) => { show_leaderboards(stdin, stdout, score_board, page); None },
+        Entry::Keys => { rebind_keys(stdin, stdout, keymap); None },
+        Entry::KeyPreset => {
+            keymap.next_preset();
+            keymap.try_save();
+            None
+        },
+        Entry::HelpPage(page) => { help::show_help(stdin, stdout, keymap, None, page); None },
+        Entry::About => { show_about(stdin, stdout); None },
+        _ => None
+    }
+}
+
+fn show_about<W:Write>(stdin:&mut AsyncReader, stdout:&mut W) {
+    let lines = vec![
+        format!("SNAKE {}", env!("CARGO_PKG_VERSION")),
+        String::new(),
+        "Scores and presets are kept in".to_string(),
+        storage::data_dir().display().to_string(),
+        String::new(),
+        "any key: back".to_string()
+    ];
+
+    let content = boxed(&lines);
+    wait_char_fitting(stdin, stdout, text_size(&content), |stdout| {
+        write!(stdout, "{}", clear::All).unwrap();
+        CenteredPanel { content: content.iter().map(String::as_str).collect(), frame: Frame::new((1,1), terminal_size().unwrap()) }.render(stdout);
+        stdout.flush().unwrap();
+    });
+}
+
+// The levels with their sizes and best scores, the first nine with a
+// digit, and the difficulty they are played at.
+fn levels_menu(score_board: &ScoreBoard, levels: &[Level], difficulty: Difficulty) -> Menu<Entry> {
+    let mut items: Vec<MenuItem<Entry>> = levels.iter().enumerate()
+        .map(|(index, level)| {
+            let label = format!("{:12} {:>2}x{:<2}  best: {:>4}", level.name, level.size.0, level.size.1, score_board.level_best(&level.name));
+            MenuItem::new(char::from_digit(index as u32 + 1, 10), &label, Entry::Level(index))
+        })
+        .collect();
+    items.push(MenuItem::new(Some('d'), &format!("DIFFICULTY: {}", difficulty.to_string().to_uppercase()), Entry::LevelDifficulty));
+    items.push(MenuItem::back());
+
+    Menu::new("LEVELS", items)
+}
+
+// Lets the player pick a level and a difficulty. Returns `None` when the
+// player backs out.
+fn pick_level<W:Write>(stdin:&mut AsyncReader, stdout:&mut W, score_board: &ScoreBoard, levels: &[Level]) -> Option<MenuAction> {
+    let mut difficulty = Difficulty::Easy;
+    let mut selected = 0;
+
+    loop {
+        match choose_from(stdin, stdout, &mut selected, || levels_menu(score_board, levels, difficulty))? {
+            Entry::Level(index) => return Some(MenuAction::StartLevel(difficulty, index)),
+            Entry::LevelDifficulty => {
+                difficulty = match difficulty {
+                    Difficulty::Easy => Difficulty::Hard,
+                    Difficulty::Hard => Difficulty::Easy
+                }
             },
             _ => ()
         }
     }
 }
 
+
+// Games, time, points and more, overall and then for each group of modes
+// and each level, a page at a time. Below them is a chart of the latest
+// scores.
+fn show_stats<W:Write>(stdin:&mut AsyncReader, stdout:&mut W, stats: &Stats) {
+    let tables = stats.tables();
+    let bar = theme::current().wall.glyph.chars().next().unwrap_or('#');
+    let mut page = 0;
+
+    loop {
+        let (title, totals) = &tables[page];
+        let time = totals.duration;
+        let mut lines = vec![
+            format!("STATISTICS: {} ({}/{})", title, page + 1, tables.len()),
+            String::new(),
+            format!("{:14} {}", "games", totals.games),
+            format!("{:14} {}:{:02}:{:02}", "time played", time / 3600, time / 60 % 60, time % 60),
+            format!("{:14} {}", "average score", totals.average()),
+            format!("{:14} {}", "longest snake", totals.longest)
+        ];
+
+        let eaten: Vec<String> = totals.eaten.iter()
+            .map(|(item, count)| format!("{} {}", count, item.split_whitespace().next().unwrap_or(item).to_lowercase()))
+            .collect();
+        for (index, chunk) in eaten.chunks(4).enumerate() {
+            lines.push(format!("{:14} {}", if index == 0 { "eaten" } else { "" }, chunk.join(", ")));
+        }
+
+        let endings: Vec<String> = totals.endings.iter().map(|(ending, count)| format!("{} {}", ending, count)).collect();
+        lines.push(format!("{:14} {}", "endings", endings.join(", ")));
+
+        if !totals.history.is_empty() {
+            lines.push(String::new());
+            lines.push("latest scores".to_string());
+            lines.extend(bar_chart(&totals.history, STATS_CHART_ROWS, bar));
+        }
+
+        lines.push(String::new());
+        lines.push("left/right: page  esc: back".to_string());
+
+        let content = boxed(&lines);
+        let draw = |stdout: &mut W| {
+            let panel = CenteredPanel {
+                content: content.iter().map(String::as_str).collect(),
+                frame: Frame::new((1,1), terminal_size().unwrap())
+            };
+
+            write!(stdout, "{}", clear::All).unwrap();
+            panel.render(stdout);
+            stdout.flush().unwrap();
+        };
+
+        for key in wait_keys_fitting(stdin, stdout, text_size(&content), draw) {
+            match key {
+                Key::Right | Key::Char('n') => page = (page + 1) % tables.len(),
+                Key::Left | Key::Char('p') => page = (page + tables.len() - 1) % tables.len(),
+                Key::Esc | Key::Backspace | Key::Char('q') => return,
+                _ => ()
+            }
+        }
+    }
+}
+
+// The leaderboards of the modes and levels that have any games.
+fn played_tables(score_board: &ScoreBoard) -> Vec<(String, &LBScore)> {
+    score_board.tables().into_iter().filter(|(_, score)| !score.top.is_empty()).collect()
+}
+
+// One leaderboard at a time, starting from `page`. The details of one game
+// are shown below the table; up and down, or digits, pick which.
+fn show_leaderboards<W:Write>(stdin:&mut AsyncReader, stdout:&mut W, score_board: &ScoreBoard, mut page: usize) {
+    let tables = played_tables(score_board);
+    let mut selected = 0;
+
+    loop {
+        let mut lines = vec![
+            "TOP SCORES".to_string(),
+            String::new()
+        ];
+
+        match tables.get(page) {
+            None => lines.push("No games yet".to_string()),
+            Some((title, score)) => {
+                lines.push(format!("{} ({}/{})", title, page + 1, tables.len()));
+                lines.push(String::new());
+
+                for (rank, record) in score.top.iter().enumerate() {
+                    let marker = if rank == selected { '>' } else { ' ' };
+                    lines.push(format!("{} {:>2}. {}", marker, rank + 1, record.summary()));
+                }
+
+                lines.push(String::new());
+                lines.push(score.top[selected].details());
+            }
+        }
+
+        lines.push(String::new());
+        lines.push("left/right: table  up/down: game  esc: back".to_string());
+
+        let content = boxed(&lines);
+        let draw = |stdout: &mut W| {
+            let panel = CenteredPanel {
+                content: content.iter().map(String::as_str).collect(),
+                frame: Frame::new((1,1), terminal_size().unwrap())
+            };
+
+            write!(stdout, "{}", clear::All).unwrap();
+            panel.render(stdout);
+            stdout.flush().unwrap();
+        };
+
+        for key in wait_keys_fitting(stdin, stdout, text_size(&content), draw) {
+            let entries = tables.get(page).map_or(0, |(_, score)| score.top.len());
+
+            match key {
+                Key::Right | Key::Char('n') if !tables.is_empty() => { page = (page + 1) % tables.len(); selected = 0; },
+                Key::Left | Key::Char('p') if !tables.is_empty() => { page = (page + tables.len() - 1) % tables.len(); selected = 0; },
+                Key::Down if entries > 0 => selected = (selected + 1) % entries,
+                Key::Up if entries > 0 => selected = (selected + entries - 1) % entries,
+                Key::Char(c @ '0'..='9') => {
+                    let index = (c as usize + 9 - '0' as usize) % 10;
+                    if index < entries {
+                        selected = index;
+                    }
+                },
+                Key::Esc | Key::Backspace | Key::Char('q') => return,
+                _ => ()
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+// A list of entries to pick from, with the arrow keys and enter or with
+// each entry's own key. The keymap plays no part: its keys would clash with
+// the entries' own. The box around it is sized to the longest line.
+
+use std::io::Write;
+use termion::{ cursor, style, event::Key };
+
+use crate::io::renderable::{ Frame, boxed };
+use crate::io::theme;
+
+pub(crate) struct MenuItem<T> {
+    pub(crate) key: Option<char>,
+    pub(crate) label: String,
+    // `None` goes back to where the menu was opened from.
+    action: Option<T>
+}
+
+impl<T> MenuItem<T> {
+    pub(crate) fn new(key: Option<char>, label: &str, action: T) -> MenuItem<T> {
+        MenuItem { key, label: label.to_string(), action: Some(action) }
+    }
+
+    pub(crate) fn back() -> MenuItem<T> {
+        MenuItem { key: Some('q'), label: "BACK".to_string(), action: None }
+    }
+}
+
+// What a key did to a menu.
+#[derive(PartialEq, Debug)]
+pub(crate) enum Choice<T> {
+    Pick(T),
+    Back,
+    Stay
+}
+
+pub(crate) struct Menu<T> {
+    pub(crate) title: String,
+    pub(crate) items: Vec<MenuItem<T>>,
+    // Lines shown below the entries.
+    pub(crate) footer: Vec<String>,
+    pub(crate) selected: usize
+}
+
+impl<T: Clone> Menu<T> {
+    pub(crate) fn new(title: &str, items: Vec<MenuItem<T>>) -> Menu<T> {
+        Menu { title: title.to_string(), items, footer: Vec::new(), selected: 0 }
+    }
+
+    // Picks the entry with `key` as its shortcut. Otherwise up and down move
+    // through the entries; enter or right picks the highlighted one, while
+    // esc, left and backspace go back.
+    pub(crate) fn handle(&mut self, key: Key) -> Choice<T> {
+        if let Some(index) = self.items.iter().position(|item| item.key.is_some_and(|c| Key::Char(c) == key)) {
+            self.selected = index;
+            return self.choose();
+        }
+
+        let count = self.items.len();
+        if count == 0 {
+            return if key == Key::Esc { Choice::Back } else { Choice::Stay };
+        }
+
+        match key {
+            Key::Up => { self.selected = (self.selected + count - 1) % count; Choice::Stay },
+            Key::Down => { self.selected = (self.selected + 1) % count; Choice::Stay },
+            Key::Char('\n') | Key::Right => self.choose(),
+            Key::Esc | Key::Left | Key::Backspace => Choice::Back,
+            _ => Choice::Stay
+        }
+    }
+
+    fn choose(&self) -> Choice<T> {
+        match &self.items[self.selected].action {
+            Some(action) => Choice::Pick(action.clone()),
+            None => Choice::Back
+        }
+    }
+
+    // The boxed menu, one line per entry with the highlighted one marked.
+    pub(crate) fn lines(&self) -> Vec<String> {
+        let mut lines = vec![self.title.clone(), String::new()];
+
+        for (index, item) in self.items.iter().enumerate() {
+            let marker = if index == self.selected { '>' } else { ' ' };
+            let key = item.key.map_or("  ".to_string(), |c| format!("{}.", c));
+            lines.push(format!("{} {} {}", marker, key, item.label));
+        }
+
+        if !self.footer.is_empty() {
+            lines.push(String::new());
+            lines.extend(self.footer.iter().cloned());
+        }
+
+        boxed(&lines)
+    }
+
+    // Draws the menu centered in `frame`, the highlighted entry inverted.
+    pub(crate) fn render<W: Write>(&self, stdout: &mut W, frame: Frame) {
+        let lines = self.lines();
+        let width = lines[0].chars().count() as u16;
+        let left = frame.pos.0 + frame.size.0.saturating_sub(width) / 2;
+        let top = frame.pos.1 + frame.size.1.saturating_sub(lines.len() as u16) / 2;
+        let theme = theme::current();
+
+        for (row, line) in (top..).zip(&lines) {
+            write!(stdout, "{}{}", cursor::Goto(left, row), theme.boxes(line)).unwrap();
+        }
+
+        // Past the border and the marker, up to the padding on the right.
+        let selected: String = lines[self.selected + 3].chars().skip(5).take(width as usize - 7).collect();
+        write!(stdout, "{}{}{}{}", cursor::Goto(left + 5, top + self.selected as u16 + 3), style::Invert, selected, style::Reset).unwrap();
+    }
+}